@@ -1,19 +1,25 @@
+use std::convert::TryFrom;
 use std::fs;
 use std::path::Path;
 
+use error::Error;
+use util;
+
 pub struct AttachedFile {
     pub path: String,
     pub name: String,
     content: Vec<u8>,
 }
 
-impl From<&str> for AttachedFile {
-    fn from(path: &str) -> Self {
+impl TryFrom<&str> for AttachedFile {
+    type Error = Error;
+
+    fn try_from(path: &str) -> Result<Self, Error> {
         let content = fs::read(path);
 
         let content: Vec<u8> = match content {
             Ok(inner) => inner,
-            Err(_) => error!("Failed to read attatched file {}", path),
+            Err(e) => return Result::Err(Error::Io(path.to_string(), e)),
         };
 
         let name = Path::new(path)
@@ -23,11 +29,11 @@ impl From<&str> for AttachedFile {
             .unwrap()
             .to_string();
 
-        AttachedFile {
+        Result::Ok(AttachedFile {
             content,
             name,
             path: path.to_string(),
-        }
+        })
     }
 }
 
@@ -35,6 +41,8 @@ impl AttachedFile {
     pub fn get_button_html(&self) -> String {
         let data = base64::encode(&self.content);
 
-        format!(" <a href=\"data:application/octet-stream;base64,{}\" download=\"{}\" class=\"btn btn-success\">{}</a> ", data, self.name, self.name)
+        let name = util::escape_html(&self.name);
+
+        format!(" <a href=\"data:application/octet-stream;base64,{}\" download=\"{}\" class=\"btn btn-success\">{}</a> ", data, name, name)
     }
 }
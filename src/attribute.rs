@@ -1,3 +1,7 @@
+use std::fmt;
+
+use error::{Diagnostics, Error, Result};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Attribute {
     Hide,
@@ -8,11 +12,14 @@ pub enum Attribute {
     Delta,
     Zero,
     Log,
+    State,
+    Bool,
     Join(String),
+    XaxisTopic(String),
 }
 
 impl Attribute {
-    pub fn from(attribute_text: &str) -> Result<Attribute, ()> {
+    pub fn from(attribute_text: &str) -> Result<Attribute> {
         if attribute_text.eq("hide") {
             return Result::Ok(Attribute::Hide);
         }
@@ -37,14 +44,81 @@ impl Attribute {
         if attribute_text.eq("log") {
             return Result::Ok(Attribute::Log);
         }
+        if attribute_text.eq("state") {
+            return Result::Ok(Attribute::State);
+        }
+        if attribute_text.eq("bool") {
+            return Result::Ok(Attribute::Bool);
+        }
         if attribute_text.starts_with("join:") {
             let (_, right) = attribute_text.split_at(5);
             if right.is_empty() {
-                error!("Failed to join topic: {}", attribute_text);
+                return Result::Err(Error::EmptyJoin(attribute_text.to_string()));
             }
             return Result::Ok(Attribute::Join(right.to_string()));
         }
 
-        Result::Err(())
+        if attribute_text.starts_with("xaxis:") {
+            let (_, right) = attribute_text.split_at(6);
+            if !right.is_empty() {
+                return Result::Ok(Attribute::XaxisTopic(right.to_string()));
+            }
+        }
+
+        Result::Err(Error::UnknownAttribute(attribute_text.to_string()))
+    }
+
+    /// Parses `attr_texts` onto the end of `attrs`, unknown and duplicate attributes are skipped
+    pub fn merge(
+        attrs: &mut Vec<Attribute>,
+        attr_texts: &[String],
+        topic_name: &str,
+        diag: &mut Diagnostics,
+    ) -> Result<()> {
+        for attr_text in attr_texts {
+            let attr = match Attribute::from(attr_text) {
+                Ok(attr) => attr,
+                Err(Error::UnknownAttribute(_)) => {
+                    diag.warn(format!(
+                        "Failed to parse attribute {}, skipping it",
+                        attr_text
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    diag.error(e)?;
+                    continue;
+                }
+            };
+            if attrs.contains(&attr) {
+                diag.warn(format!(
+                    "Duplicate attribute \"{}\" on topic {}, ignoring duplicate",
+                    attr_text, topic_name
+                ));
+                continue;
+            }
+            attrs.push(attr);
+        }
+        Result::Ok(())
+    }
+}
+
+/// Writes the attribute the way `Attribute::from` reads it
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Attribute::Hide => write!(f, "hide"),
+            Attribute::Area => write!(f, "area"),
+            Attribute::Xaxis => write!(f, "xaxis"),
+            Attribute::Differentiate => write!(f, "differentiate"),
+            Attribute::Integrate => write!(f, "integrate"),
+            Attribute::Delta => write!(f, "delta"),
+            Attribute::Zero => write!(f, "zero"),
+            Attribute::Log => write!(f, "log"),
+            Attribute::State => write!(f, "state"),
+            Attribute::Bool => write!(f, "bool"),
+            Attribute::Join(ref name) => write!(f, "join:{}", name),
+            Attribute::XaxisTopic(ref name) => write!(f, "xaxis:{}", name),
+        }
     }
 }
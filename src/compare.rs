@@ -0,0 +1,205 @@
+use std::path::Path;
+
+use error::{Diagnostics, Error, Result};
+use graph::{Graph, Series};
+use input::{self, Input, Log, Value, STDIO_PATH};
+use xaxis::XAxis;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Align {
+    None,
+    Start,
+    Event(String),
+}
+
+impl Align {
+    pub fn from(align_text: &str) -> Result<Align> {
+        if align_text.eq("none") {
+            return Result::Ok(Align::None);
+        }
+        if align_text.eq("start") {
+            return Result::Ok(Align::Start);
+        }
+        if align_text.starts_with("event:") {
+            let (_, right) = align_text.split_at(6);
+            if !right.is_empty() {
+                return Result::Ok(Align::Event(right.to_string()));
+            }
+        }
+
+        Result::Err(Error::UnknownAlign(align_text.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub struct Run {
+    pub name: String,
+    pub input: Input,
+}
+
+impl Run {
+    pub fn new(input_path: &str, input: Input) -> Self {
+        let name = if input_path == STDIO_PATH {
+            "stdin".to_string()
+        } else {
+            let path = Path::new(input::display_name(input_path));
+            path.file_name()
+                .map_or(input_path.to_string(), |name| {
+                    name.to_string_lossy().into_owned()
+                })
+        };
+        Run { name, input }
+    }
+
+    /// How far this run is moved along its x-axis so all runs line up
+    fn offset(&self, align: &Align, xaxis: &XAxis, diag: &mut Diagnostics) -> f64 {
+        let rows = self.rows();
+        match *align {
+            Align::None => 0f64,
+            Align::Start => {
+                if rows == 0 {
+                    0f64
+                } else {
+                    xaxis.at(0)
+                }
+            }
+            Align::Event(ref text) => {
+                let event = self
+                    .input
+                    .logs
+                    .iter()
+                    .flat_map(|log| log.data.iter())
+                    .filter(|line| line.1.contains(text.as_str()))
+                    .map(|line| line.0 as usize)
+                    .min();
+                match event {
+                    Some(row) => xaxis.at(row),
+                    None => {
+                        diag.warn(format!(
+                            "No event matching \"{}\" in {}, leaving it unaligned",
+                            text, self.name
+                        ));
+                        0f64
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rows in the run, logs reach past the topics when every topic is hidden
+    fn rows(&self) -> usize {
+        let topic_rows = self
+            .input
+            .topics
+            .iter()
+            .map(|topic| topic.data.len())
+            .max()
+            .unwrap_or(0);
+        let log_rows = self
+            .input
+            .logs
+            .iter()
+            .flat_map(|log| log.data.iter())
+            .map(|line| line.0 as usize + 1)
+            .max()
+            .unwrap_or(0);
+        topic_rows.max(log_rows)
+    }
+}
+
+/// Generates the graphs of every run and overlays them, one series per run.
+///
+/// Values and logs are tagged with the run they came from so they can be shown side by side.
+pub fn gen_compare(
+    runs: Vec<Run>,
+    align: &Align,
+    diag: &mut Diagnostics,
+) -> Result<(Vec<Graph>, Vec<Value>, Vec<Log>)> {
+    for (i, run) in runs.iter().enumerate() {
+        if runs[..i].iter().any(|r| r.name.eq(&run.name)) {
+            diag.warn(format!("Comparing multiple runs with same name: {}", run.name));
+        }
+    }
+
+    let mut merged: Vec<Graph> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+    let mut logs: Vec<Log> = Vec::new();
+
+    for run in runs {
+        let (graphs, xaxis) = Graph::gen_graphs(&run.input.topics, diag)?;
+
+        let offset = run.offset(align, &xaxis, diag);
+        let xaxis = xaxis.shifted(offset, run.rows());
+
+        for mut graph in graphs {
+            let existing = merged.iter().position(|g| g.name.eq(&graph.name));
+
+            // State indices are per run, so they are moved onto the first run's list of states
+            let state_map: Option<Vec<f64>> = match (existing, graph.states.as_ref()) {
+                (Some(existing), Some(states)) => {
+                    let merged_states = merged[existing].states.get_or_insert_with(Vec::new);
+                    Some(
+                        states
+                            .iter()
+                            .map(|state| {
+                                match merged_states.iter().position(|s| s.eq(state)) {
+                                    Some(index) => index as f64,
+                                    None => {
+                                        merged_states.push(state.clone());
+                                        (merged_states.len() - 1) as f64
+                                    }
+                                }
+                            })
+                            .collect(),
+                    )
+                }
+                _ => None,
+            };
+            let remap = |y: f64| match state_map {
+                Some(ref map) if !y.is_nan() => map[y as usize],
+                _ => y,
+            };
+
+            let single = graph.series.len() == 1;
+            let series = ::std::mem::take(&mut graph.series)
+                .into_iter()
+                .map(|s| Series {
+                    name: if single {
+                        run.name.clone()
+                    } else {
+                        format!("{}: {}", run.name, s.name)
+                    },
+                    data: s.data.iter().map(|&(x, y)| (x - offset, remap(y))).collect(),
+                })
+                .collect::<Vec<Series>>();
+
+            if let Some(existing) = existing {
+                let existing = &mut merged[existing];
+                if existing.unit.ne(&graph.unit) {
+                    diag.warn(format!(
+                        "Comparing different units for {}: {} and {}",
+                        existing.name, existing.unit, graph.unit
+                    ));
+                }
+                existing.series.extend(series);
+                continue;
+            }
+
+            graph.series = series;
+            merged.push(graph);
+        }
+
+        for mut value in run.input.values {
+            value.run = Option::Some(run.name.clone());
+            values.push(value);
+        }
+
+        for mut log in run.input.logs {
+            log.apply_xaxis(&xaxis);
+            log.run = Option::Some(run.name.clone());
+            logs.push(log);
+        }
+    }
+
+    Result::Ok((merged, values, logs))
+}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use serde_json;
+
+use attribute::Attribute;
+use error::{Diagnostics, Error, Result};
+use input::Topic;
+use util;
+
+/// Units and attributes given to topics outside of the log itself.
+///
+/// Read from a JSON file like
+/// `{"topics": [{"name": "Drivetrain/*", "unit": "m/s", "attrs": ["differentiate"]}]}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub topics: Vec<TopicConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopicConfig {
+    /// Topic name, `*` and `?` match like a file glob
+    pub name: String,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub attrs: Vec<String>,
+}
+
+impl Config {
+    pub fn from_path(config_path: &str) -> Result<Config> {
+        let file = match File::open(config_path) {
+            Ok(file) => file,
+            Err(e) => return Result::Err(Error::Io(config_path.to_string(), e)),
+        };
+        match serde_json::from_reader(BufReader::new(file)) {
+            Ok(config) => Result::Ok(config),
+            Err(e) => Result::Err(Error::Config(config_path.to_string(), e)),
+        }
+    }
+
+    /// Adds an entry from a `<topic>=<attr>,<attr>` command line option
+    pub fn push_attrs(&mut self, option_text: &str) -> Result<()> {
+        let (name, attrs) = match util::split_option(option_text) {
+            Some(option) => option,
+            None => return Result::Err(Error::TopicOption(option_text.to_string())),
+        };
+        self.topics.push(TopicConfig {
+            name,
+            unit: Option::None,
+            attrs: attrs
+                .split(',')
+                .map(str::trim)
+                .filter(|attr| !attr.is_empty())
+                .map(str::to_string)
+                .collect(),
+        });
+        Result::Ok(())
+    }
+
+    /// Adds an entry from a `<topic>=<unit>` command line option
+    pub fn push_unit(&mut self, option_text: &str) -> Result<()> {
+        let (name, unit) = match util::split_option(option_text) {
+            Some(option) => option,
+            None => return Result::Err(Error::TopicOption(option_text.to_string())),
+        };
+        self.topics.push(TopicConfig {
+            name,
+            unit: Option::Some(unit),
+            attrs: Vec::new(),
+        });
+        Result::Ok(())
+    }
+
+    pub fn push_xaxis(&mut self, name: &str) {
+        self.topics.push(TopicConfig {
+            name: name.to_string(),
+            unit: Option::None,
+            attrs: vec!["xaxis".to_string()],
+        });
+    }
+
+    /// Applies every matching entry in order, later units win and attributes are added on
+    pub fn apply(&self, topics: &mut [Topic], diag: &mut Diagnostics) -> Result<()> {
+        for entry in &self.topics {
+            let mut matched = false;
+            for topic in topics
+                .iter_mut()
+                .filter(|t| util::glob_match(&entry.name, &t.name))
+            {
+                matched = true;
+                if let Some(ref unit) = entry.unit {
+                    topic.unit = if unit.is_empty() {
+                        ::UNITLESS.to_string()
+                    } else {
+                        unit.clone()
+                    };
+                }
+                Attribute::merge(&mut topic.attrs, &entry.attrs, &topic.name, diag)?;
+            }
+            if !matched {
+                diag.warn(format!("Config entry {} matches no topic", entry.name));
+            }
+        }
+        Result::Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use std::io::prelude::*;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json;
+
+const SECTION_F64: u8 = 0;
+const SECTION_F32: u8 = 1;
+
+/// Numbers behind every chart, embedded once as binary instead of as text in each chart.
+///
+/// Charts refer to arrays in here as `[section, start, len]`. The report's CSV is stored at the
+/// end of the same buffer so one compression covers everything.
+#[derive(Debug, Default)]
+pub struct DataStore {
+    f64s: Vec<f64>,
+    f32s: Vec<f32>,
+    /// Store y values as 32-bit floats, x values are always 64-bit so timestamps stay exact
+    float32: bool,
+}
+
+impl DataStore {
+    pub fn new(float32: bool) -> Self {
+        DataStore {
+            float32,
+            ..DataStore::default()
+        }
+    }
+
+    fn push_f64(&mut self, values: &[f64]) -> serde_json::Value {
+        let start = self.f64s.len();
+        self.f64s.extend_from_slice(values);
+        json!([SECTION_F64, start, values.len()])
+    }
+
+    fn push_f32(&mut self, values: &[f64]) -> serde_json::Value {
+        let start = self.f32s.len();
+        self.f32s.extend(values.iter().map(|v| *v as f32));
+        json!([SECTION_F32, start, values.len()])
+    }
+
+    /// Adds a line and returns the `[x, y]` pair of references the page reads it back from
+    pub fn push_points(&mut self, data: &[(f64, f64)]) -> serde_json::Value {
+        let xs: Vec<f64> = data.iter().map(|p| p.0).collect();
+        let ys: Vec<f64> = data.iter().map(|p| p.1).collect();
+        let x_ref = self.push_f64(&xs);
+        let y_ref = if self.float32 {
+            self.push_f32(&ys)
+        } else {
+            self.push_f64(&ys)
+        };
+        json!([x_ref, y_ref])
+    }
+
+    /// Base64 of the whole buffer with `csv_text` and then `augmented_csv` appended, and the byte
+    /// lengths of its sections
+    pub fn encode(
+        &self,
+        csv_text: &str,
+        augmented_csv: Option<&str>,
+        compress: bool,
+    ) -> (String, serde_json::Value) {
+        let augmented_csv = augmented_csv.unwrap_or("");
+        let mut bytes: Vec<u8> = Vec::with_capacity(
+            8 * self.f64s.len() + 4 * self.f32s.len() + csv_text.len() + augmented_csv.len(),
+        );
+        for value in &self.f64s {
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        for value in &self.f32s {
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        bytes.extend_from_slice(csv_text.as_bytes());
+        bytes.extend_from_slice(augmented_csv.as_bytes());
+
+        if compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&bytes).unwrap();
+            bytes = encoder.finish().unwrap();
+        }
+
+        let sections = json!({
+            "f64": self.f64s.len(),
+            "f32": self.f32s.len(),
+            "csv": csv_text.len(),
+            "augmented": augmented_csv.len(),
+            "gzip": compress
+        });
+
+        (BASE64.encode(&bytes), sections)
+    }
+}
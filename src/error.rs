@@ -0,0 +1,226 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::result;
+
+use csv;
+use regex;
+use serde_json;
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(String, io::Error),
+    Write(String, io::Error),
+    BagWrite(io::Error),
+    JsonWrite(serde_json::Error),
+    JsonHeader(serde_json::Error),
+    Config(String, serde_json::Error),
+    TopicOption(String),
+    ValueOption(String),
+    Csv(csv::Error),
+    RowLength { row: usize, header: usize },
+    MissingColumn(String),
+    DuplicateColumn(String),
+    ParseDouble { value: String, trimmed: bool },
+    DuplicateTopic(String),
+    DuplicateValue(String),
+    UnknownAttribute(String),
+    LogAttributes(String),
+    BagStarted(String),
+    MultipleXaxis,
+    MissingXaxis(String),
+    AxisLength {
+        topic: String,
+        len: usize,
+        axis_len: usize,
+    },
+    EmptyJoin(String),
+    NonJoinableGraph(String),
+    NoDataPoints,
+    UnknownAlign(String),
+    UnknownBound(String),
+    EmptyWindow,
+    WindowTopic(String),
+    Pattern(String, regex::Error),
+    UnknownFormat(String),
+    StdoutFormat(String),
+    Wpilog(String),
+    Multiple(Vec<Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(ref path, ref e) => write!(f, "Failed to open file \"{}\": {}", path, e),
+            Error::Write(ref path, ref e) => {
+                write!(f, "Failed to write file \"{}\": {}", path, e)
+            }
+            Error::BagWrite(ref e) => write!(f, "Failed to write bag: {}", e),
+            Error::JsonWrite(ref e) => write!(f, "Failed to write JSON: {}", e),
+            Error::JsonHeader(ref e) => write!(
+                f,
+                "Failed to parse json header: {} (if its a CSV file use --csv)",
+                e
+            ),
+            Error::Config(ref path, ref e) => {
+                write!(f, "Failed to parse config file \"{}\": {}", path, e)
+            }
+            Error::TopicOption(ref text) => {
+                write!(f, "Expected <topic>=<value> but got \"{}\"", text)
+            }
+            Error::ValueOption(ref text) => {
+                write!(f, "Expected <name>=<value> but got \"{}\"", text)
+            }
+            Error::Csv(ref e) => write!(f, "{}", e),
+            Error::RowLength { row, header } => write!(
+                f,
+                "Row length ({}) is longer than CSV header length ({})",
+                row, header
+            ),
+            Error::MissingColumn(ref name) => write!(f, "Can't find topic \"{}\" in CSV", name),
+            Error::DuplicateColumn(ref name) => {
+                write!(f, "Multiple columns \"{}\" found in CSV", name)
+            }
+            Error::ParseDouble { ref value, trimmed } => {
+                if *trimmed {
+                    write!(f, "Failed to parse \"{}\" as a double", value)
+                } else {
+                    write!(
+                        f,
+                        "Failed to parse \"{}\" as a double (maybe try --trim-doubles or hide topic)",
+                        value
+                    )
+                }
+            }
+            Error::DuplicateTopic(ref name) => {
+                write!(f, "Duplicate topic entry in JSON header for {}", name)
+            }
+            Error::DuplicateValue(ref name) => {
+                write!(f, "Duplicate value {} with different values", name)
+            }
+            Error::UnknownAttribute(ref text) => write!(f, "Failed to parse attribute {}", text),
+            Error::LogAttributes(ref name) => {
+                write!(f, "Too many attributes on log topic {}", name)
+            }
+            Error::BagStarted(ref name) => {
+                write!(f, "Can't add {} after the bag's first row was written", name)
+            }
+            Error::MultipleXaxis => write!(f, "Multiple topics with xaxis attribute"),
+            Error::MissingXaxis(ref name) => write!(f, "Can't find x-axis topic \"{}\"", name),
+            Error::AxisLength {
+                ref topic,
+                len,
+                axis_len,
+            } => write!(
+                f,
+                "Topic {} has {} points but its x-axis has {}",
+                topic, len, axis_len
+            ),
+            Error::EmptyJoin(ref text) => write!(f, "Failed to join topic: {}", text),
+            Error::NonJoinableGraph(ref name) => {
+                write!(f, "Attempting to join to non-joinable graph {}", name)
+            }
+            Error::NoDataPoints => write!(
+                f,
+                "No data points found in bag file! Robot code may have ended early."
+            ),
+            Error::UnknownAlign(ref text) => write!(
+                f,
+                "Unknown alignment {} (expected none, start or event:<text>)",
+                text
+            ),
+            Error::UnknownBound(ref text) => write!(
+                f,
+                "Unknown window bound {} (expected an x-axis position or row:<index>)",
+                text
+            ),
+            Error::EmptyWindow => write!(f, "No rows are inside the window"),
+            Error::WindowTopic(ref name) => {
+                write!(f, "Can't find topic \"{}\" to crop to", name)
+            }
+            Error::Pattern(ref text, ref e) => {
+                write!(f, "Failed to parse pattern \"{}\": {}", text, e)
+            }
+            Error::UnknownFormat(ref text) => {
+                write!(f, "Unknown output format {} (expected html, svg, markdown or json)", text)
+            }
+            Error::StdoutFormat(ref text) => {
+                write!(f, "Can't write {} output to stdout, give an output folder", text)
+            }
+            Error::Wpilog(ref message) => write!(f, "Failed to parse wpilog: {}", message),
+            Error::Multiple(ref errors) => {
+                let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonHeader(e)
+    }
+}
+
+/// Warnings and errors reported while turning a log into graphs.
+///
+/// By default the first error is returned straight away. A collecting
+/// instance records errors and keeps going so a whole stage can be checked
+/// at once with `check`.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub warnings: Vec<String>,
+    /// Progress messages only shown in verbose output
+    pub notes: Vec<String>,
+    pub errors: Vec<Error>,
+    collect: bool,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Diagnostics::default()
+    }
+
+    pub fn collecting() -> Self {
+        Diagnostics {
+            collect: true,
+            ..Diagnostics::default()
+        }
+    }
+
+    pub fn warn<S: Into<String>>(&mut self, message: S) {
+        self.warnings.push(message.into());
+    }
+
+    pub fn note<S: Into<String>>(&mut self, message: S) {
+        self.notes.push(message.into());
+    }
+
+    pub fn error(&mut self, error: Error) -> Result<()> {
+        if self.collect {
+            self.errors.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    pub fn check(&mut self) -> Result<()> {
+        match self.errors.len() {
+            0 => Ok(()),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(Error::Multiple(mem::take(&mut self.errors))),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use csv;
+use serde_json;
+
+use error::{Diagnostics, Error, Result};
+use folder::Folder;
+use graph::Graph;
+use util;
+use xaxis::XAxis;
+
+/// Version of the JSON export, raised whenever a field is renamed, removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Top level object of the JSON export, the schema is described in the README
+#[derive(Serialize, Debug)]
+pub struct Report<'a> {
+    pub schema: u32,
+    pub badlogvis: &'a str,
+    pub title: &'a str,
+    /// JSON header of a bag input, as text
+    pub header: Option<&'a str>,
+    pub folders: &'a [Folder],
+}
+
+/// The processed report as JSON, gaps in series are written as `null`
+pub fn gen_json(input: &str, folders: &[Folder], json_header: Option<&str>) -> String {
+    let report = Report {
+        schema: SCHEMA_VERSION,
+        badlogvis: ::VERSION,
+        title: input,
+        header: json_header,
+        folders,
+    };
+    // Only strings, numbers and tuples are serialized, which can't fail
+    serde_json::to_string(&report).unwrap()
+}
+
+/// The input's CSV with a column added for each series of the derived and joined graphs,
+/// resampled onto `xaxis` so every row has the value the chart shows there
+pub fn gen_augmented_csv(
+    csv_text: &str,
+    graphs: &[Graph],
+    xaxis: &XAxis,
+    diag: &mut Diagnostics,
+) -> Result<String> {
+    let mut columns: Vec<(String, &[(f64, f64)])> = Vec::new();
+    for graph in graphs.iter().filter(|g| g.virt) {
+        // The rows only have positions on the main axis
+        if graph.x_unit.ne(&xaxis.name) {
+            diag.warn(format!(
+                "Graph {} has its own x-axis and is left out of the exported CSV",
+                graph.name
+            ));
+            continue;
+        }
+        for s in &graph.series {
+            let name = if graph.series.len() > 1 {
+                format!("{}/{}", graph.name, s.name)
+            } else {
+                graph.name.clone()
+            };
+            columns.push((format!("{} ({})", name, graph.unit), &s.data));
+        }
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv_text.as_bytes());
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let header = reader.headers()?.clone();
+    let width = header.len();
+    let mut record: Vec<String> = header.iter().map(String::from).collect();
+    record.extend(columns.iter().map(|c| c.0.clone()));
+    writer.write_record(&record)?;
+
+    let rows = xaxis.data.as_ref().map(Vec::len);
+    for (row, cells) in reader.records().enumerate() {
+        let cells = cells?;
+        // Short rows are padded so the added columns line up
+        let mut record: Vec<String> = (0..width)
+            .map(|i| cells.get(i).unwrap_or("").to_string())
+            .collect();
+        let x = match rows {
+            Some(rows) if row >= rows => f64::NAN,
+            _ => xaxis.at(row),
+        };
+        for &(_, data) in &columns {
+            let value = util::resample(data, x);
+            record.push(if value.is_nan() {
+                String::new()
+            } else {
+                value.to_string()
+            });
+        }
+        writer.write_record(&record)?;
+    }
+
+    match writer.into_inner() {
+        Ok(bytes) => Result::Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        Err(e) => Result::Err(Error::Csv(e.into_error().into())),
+    }
+}
@@ -0,0 +1,168 @@
+use regex::Regex;
+
+use attribute::Attribute;
+use error::{Diagnostics, Error, Result};
+use input::Input;
+use util;
+
+/// A name pattern, `*` and `?` match like a file glob and `regex:<expr>` is searched for as a
+/// regular expression
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn from(pattern_text: &str) -> Result<Pattern> {
+        if pattern_text.starts_with("regex:") {
+            let (_, right) = pattern_text.split_at(6);
+            return match Regex::new(right) {
+                Ok(regex) => Result::Ok(Pattern::Regex(regex)),
+                Err(e) => Result::Err(Error::Pattern(pattern_text.to_string(), e)),
+            };
+        }
+
+        Result::Ok(Pattern::Glob(pattern_text.to_string()))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match *self {
+            Pattern::Glob(ref glob) => util::glob_match(glob, name),
+            Pattern::Regex(ref regex) => regex.is_match(name),
+        }
+    }
+
+    fn text(&self) -> String {
+        match *self {
+            Pattern::Glob(ref glob) => glob.clone(),
+            Pattern::Regex(ref regex) => format!("regex:{}", regex.as_str()),
+        }
+    }
+}
+
+/// Topics, logs and values to keep by their full name
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Everything is kept when empty
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+}
+
+fn plural(count: usize, name: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, name)
+    } else {
+        format!("{} {}s", count, name)
+    }
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Filter> {
+        let patterns = |texts: &[String]| {
+            texts
+                .iter()
+                .map(|text| Pattern::from(text))
+                .collect::<Result<Vec<Pattern>>>()
+        };
+        Result::Ok(Filter {
+            include: patterns(include)?,
+            exclude: patterns(exclude)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn keeps(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+
+    /// Drops the topics, logs and values of `input` the filter doesn't keep, warning with a
+    /// summary of what was dropped from the input called `name`. The x-axis, the time columns of kept topics and every topic joined
+    /// into a kept graph are kept too, a joined graph is kept when its own name or one of its
+    /// topics is.
+    pub fn apply(&self, name: &str, input: &mut Input, diag: &mut Diagnostics) {
+        for pattern in self.include.iter().chain(&self.exclude) {
+            let matched = input
+                .topics
+                .iter()
+                .map(|t| &t.name)
+                .chain(input.logs.iter().map(|l| &l.name))
+                .chain(input.values.iter().map(|v| &v.name))
+                .any(|name| pattern.matches(name));
+            if !matched {
+                diag.warn(format!(
+                    "Pattern {} matches no topic, log or value",
+                    pattern.text()
+                ));
+            }
+        }
+
+        let mut kept: Vec<bool> = input.topics.iter().map(|t| self.keeps(&t.name)).collect();
+
+        let joins = |attrs: &[Attribute]| -> Vec<String> {
+            attrs
+                .iter()
+                .filter_map(|attr| match *attr {
+                    Attribute::Join(ref name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let mut targets: Vec<String> = Vec::new();
+        for (i, topic) in input.topics.iter().enumerate() {
+            for target in joins(&topic.attrs) {
+                if (kept[i] || self.keeps(&target)) && !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        for (i, topic) in input.topics.iter().enumerate() {
+            if joins(&topic.attrs)
+                .iter()
+                .any(|target| targets.contains(target))
+            {
+                kept[i] = true;
+            }
+        }
+
+        let time_columns: Vec<String> = input
+            .topics
+            .iter()
+            .enumerate()
+            .filter(|p| kept[p.0])
+            .filter_map(|p| p.1.xaxis_topic().map(str::to_string))
+            .collect();
+        for (i, topic) in input.topics.iter().enumerate() {
+            if topic.attrs.contains(&Attribute::Xaxis) || time_columns.contains(&topic.name) {
+                kept[i] = true;
+            }
+        }
+
+        let topics = input.topics.len();
+        let mut kept = kept.into_iter();
+        input.topics.retain(|_| kept.next().unwrap_or(true));
+        let dropped_topics = topics - input.topics.len();
+
+        let logs = input.logs.len();
+        input.logs.retain(|l| self.keeps(&l.name));
+        let dropped_logs = logs - input.logs.len();
+
+        let values = input.values.len();
+        input.values.retain(|v| self.keeps(&v.name));
+        let dropped_values = values - input.values.len();
+
+        diag.warn(format!(
+            "Filtered {}, dropping {}, {} and {} and keeping {} of {} topics",
+            name,
+            plural(dropped_topics, "topic"),
+            plural(dropped_logs, "log"),
+            plural(dropped_values, "value"),
+            input.topics.len(),
+            topics
+        ));
+    }
+}
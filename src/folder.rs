@@ -1,9 +1,11 @@
-use graph::Graph;
+use embed::DataStore;
+use error::Result;
+use graph::{Graph, RenderOptions};
 use input::Log;
 use input::Value;
-use util::hash_string;
+use util::{escape_html, escape_markdown, hash_string, markdown_code_block, markdown_table};
 
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct Folder {
     pub name: String,
     pub table: Vec<Value>,
@@ -69,26 +71,26 @@ impl Folder {
         folders
     }
 
-    pub fn gen_html(&self) -> String {
+    pub fn gen_html(&self, render: &RenderOptions, store: &mut DataStore) -> Result<String> {
         let table = gen_table(&self.table);
         let log_table = gen_log_table(&self.logs);
         let mut graph_content = String::new();
         for topic in &self.graphs {
-            graph_content += &topic.gen_highchart();
+            graph_content += &topic.gen_highchart(render, store)?;
         }
 
         if self.name.is_empty() {
-            return format!(
+            return Result::Ok(format!(
                 "{table}\n{log_table}\n{graphs}",
                 table = table,
                 graphs = graph_content,
                 log_table = log_table
-            );
+            ));
         }
 
         let collapse_name = hash_string(&self.name);
 
-        format!(
+        Result::Ok(format!(
             r##"
   <div class="panel-group">
     <div class="panel panel-default">
@@ -106,46 +108,248 @@ impl Folder {
       </div>
     </div>
   </div>"##,
-            name = self.name,
+            name = escape_html(&self.name),
             table = table,
             graphs = graph_content,
             log_table = log_table,
             collapse_name = collapse_name
+        ))
+    }
+
+    /// Folder without any scripts, showing its graphs as the given image files
+    pub fn gen_static_html(&self, images: &[String]) -> String {
+        let table = gen_table(&self.table);
+        let log_table = gen_log_table(&self.logs);
+        let graphs: String = images
+            .iter()
+            .map(|image| {
+                format!(
+                    "<p><img src=\"{}\" style=\"max-width: 100%\"></p>\n",
+                    escape_html(image)
+                )
+            })
+            .collect();
+
+        let heading = if self.name.is_empty() {
+            "".to_string()
+        } else {
+            format!("<h2>{}</h2>", escape_html(&self.name))
+        };
+
+        format!(
+            "{heading}\n{table}\n{log_table}\n{graphs}",
+            heading = heading,
+            table = table,
+            log_table = log_table,
+            graphs = graphs
         )
     }
+
+    /// Folder as a Markdown section, showing its graphs as the given image files
+    pub fn gen_markdown(&self, images: &[String]) -> String {
+        let mut out = String::new();
+        if !self.name.is_empty() {
+            out += &format!("## {}\n\n", escape_markdown(&self.name));
+        }
+        out += &gen_markdown_table(&self.table);
+        out += &gen_markdown_logs(&self.logs);
+        for (graph, image) in self.graphs.iter().zip(images) {
+            out += &format!(
+                "### {name}\n\n![{name}]({image})\n\n{summary}\n",
+                name = escape_markdown(&graph.name_base),
+                image = image,
+                summary = graph.gen_markdown_summary()
+            );
+        }
+        out
+    }
+}
+
+/// Run names in order of first appearance, empty when nothing is being compared
+fn run_names<'a, I: Iterator<Item = &'a Option<String>>>(runs: I) -> Vec<&'a str> {
+    let mut names: Vec<&str> = Vec::new();
+    for run in runs {
+        if let Some(ref run) = *run {
+            if !names.contains(&run.as_str()) {
+                names.push(run);
+            }
+        }
+    }
+    names
 }
 
 fn gen_table(values: &[Value]) -> String {
     if values.is_empty() {
         return "<!-- Empty table omitted -->\n".to_string();
     }
+
+    let runs = run_names(values.iter().map(|v| &v.run));
+    if !runs.is_empty() {
+        return gen_run_table(values, &runs);
+    }
+
     let mut rows = String::new();
     for value in values.iter() {
         rows += &format!(
             "<tr><td>{name}</td><td>{value}</td></tr>\n",
-            name = value.name_base,
-            value = value.value
+            name = escape_html(&value.name_base),
+            value = escape_html(&value.value)
         );
     }
     format!(r#"<table class="table table-striped"><thead><tr><th>Name</th><th>Value</th></tr></thead><tbody>{rows}</tbody></table>"#, rows = rows)
 }
 
+fn gen_run_table(values: &[Value], runs: &[&str]) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for value in values {
+        if !names.contains(&value.name_base.as_str()) {
+            names.push(&value.name_base);
+        }
+    }
+
+    let head: String = runs
+        .iter()
+        .map(|run| format!("<th>{}</th>", escape_html(run)))
+        .collect();
+
+    let mut rows = String::new();
+    for name in names {
+        rows += &format!("<tr><td>{}</td>", escape_html(name));
+        for run in runs {
+            let value = values
+                .iter()
+                .find(|v| v.name_base.eq(name) && v.run.as_ref().is_some_and(|r| r.eq(run)))
+                .map_or("", |v| v.value.as_str());
+            rows += &format!("<td>{}</td>", escape_html(value));
+        }
+        rows += "</tr>\n";
+    }
+    format!(r#"<table class="table table-striped"><thead><tr><th>Name</th>{head}</tr></thead><tbody>{rows}</tbody></table>"#, head = head, rows = rows)
+}
+
 fn gen_log_table(logs: &[Log]) -> String {
     let mut output = "<!-- Log Table -->\n".into();
 
+    let mut names: Vec<&str> = Vec::new();
     for log in logs {
-        let content: String = log.lines.clone().unwrap().join("\n");
+        if !names.contains(&log.name.as_str()) {
+            names.push(&log.name);
+        }
+    }
 
-        let collapse_name = hash_string(&log.name);
+    for name in names {
+        let group: Vec<&Log> = logs.iter().filter(|l| l.name.eq(name)).collect();
+        let runs = run_names(group.iter().map(|l| &l.run));
+
+        let content = if runs.is_empty() {
+            format!("<pre>{}</pre>", escape_html(&group[0].lines.clone().unwrap().join("\n")))
+        } else {
+            let head: String = runs
+                .iter()
+                .map(|run| format!("<th>{}</th>", escape_html(run)))
+                .collect();
+            let cells: String = runs
+                .iter()
+                .map(|run| {
+                    let lines = group
+                        .iter()
+                        .find(|l| l.run.as_ref().is_some_and(|r| r.eq(run)))
+                        .map_or(String::new(), |l| l.lines.clone().unwrap().join("\n"));
+                    format!("<td><pre>{}</pre></td>", escape_html(&lines))
+                })
+                .collect();
+            format!(
+                r#"<table class="table"><thead><tr>{head}</tr></thead><tbody><tr>{cells}</tr></tbody></table>"#,
+                head = head,
+                cells = cells
+            )
+        };
+
+        let collapse_name = hash_string(name);
 
         output += format!(r##"<div class="panel panel-info">
             <div class="panel-heading"><a data-toggle="collapse" href="#collapse_{collapse_name}">{name}</a></div>
             <div id="collapse_{collapse_name}" class="panel-collapse collapse in">
             <div class="panel-body">
-                <pre>{content}</pre>
+                {content}
             </div>
           </div>
-          </div>"##, name = log.name_base, content = content, collapse_name = collapse_name).as_ref();
+          </div>"##, name = escape_html(&group[0].name_base), content = content, collapse_name = collapse_name).as_ref();
+    }
+
+    output
+}
+
+fn gen_markdown_table(values: &[Value]) -> String {
+    if values.is_empty() {
+        return "".to_string();
+    }
+
+    let runs = run_names(values.iter().map(|v| &v.run));
+    if runs.is_empty() {
+        let rows: Vec<Vec<String>> = values
+            .iter()
+            .map(|v| vec![v.name_base.clone(), v.value.clone()])
+            .collect();
+        return markdown_table(&["Name", "Value"], &rows) + "\n";
+    }
+
+    let mut names: Vec<&str> = Vec::new();
+    for value in values {
+        if !names.contains(&value.name_base.as_str()) {
+            names.push(&value.name_base);
+        }
+    }
+
+    let mut head = vec!["Name"];
+    head.extend(runs.iter());
+    let rows: Vec<Vec<String>> = names
+        .iter()
+        .map(|name| {
+            let mut row = vec![name.to_string()];
+            for run in &runs {
+                let value = values
+                    .iter()
+                    .find(|v| v.name_base.eq(name) && v.run.as_ref().is_some_and(|r| r.eq(run)))
+                    .map_or("", |v| v.value.as_str());
+                row.push(value.to_string());
+            }
+            row
+        })
+        .collect();
+    markdown_table(&head, &rows) + "\n"
+}
+
+fn gen_markdown_logs(logs: &[Log]) -> String {
+    let mut output = String::new();
+
+    let mut names: Vec<&str> = Vec::new();
+    for log in logs {
+        if !names.contains(&log.name.as_str()) {
+            names.push(&log.name);
+        }
+    }
+
+    for name in names {
+        let group: Vec<&Log> = logs.iter().filter(|l| l.name.eq(name)).collect();
+        let runs = run_names(group.iter().map(|l| &l.run));
+
+        output += &format!("**{}**\n\n", escape_markdown(&group[0].name_base));
+        if runs.is_empty() {
+            output += &markdown_code_block(&group[0].lines.clone().unwrap().join("\n"));
+            output += "\n";
+            continue;
+        }
+
+        for run in runs {
+            let lines = group
+                .iter()
+                .find(|l| l.run.as_ref().is_some_and(|r| r.eq(run)))
+                .map_or(String::new(), |l| l.lines.clone().unwrap().join("\n"));
+            output += &format!("_{}_\n\n", escape_markdown(run));
+            output += &markdown_code_block(&lines);
+            output += "\n";
+        }
     }
 
     output
@@ -1,11 +1,16 @@
 use std::cmp::Ordering::Equal;
+use std::convert::TryFrom;
+
+use serde_json;
 
 use attribute::Attribute;
+use embed::DataStore;
+use error::{Diagnostics, Error, Result};
 use input::Topic;
 use util;
 use xaxis::XAxis;
 
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct Graph {
     pub name: String,
     pub name_base: String,
@@ -17,14 +22,33 @@ pub struct Graph {
     pub joinable: bool,
     pub area: bool,
     pub zero: bool,
+    pub missing: usize,
+    /// Names of the states for a state timeline, series values index into it
+    pub states: Option<Vec<String>>,
+    /// Boolean trace drawn as a 0/1 step line, several series are stacked above each other
+    pub digital: bool,
+}
+
+/// Settings for drawing graphs into the report
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    /// Most points drawn for a line before zooming in, `None` draws every point
+    pub max_points: Option<usize>,
+    /// Gzip the embedded data, the page unpacks it with `DecompressionStream`
+    pub compress: bool,
+    /// Embed y values as 32-bit floats
+    pub float32: bool,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct Series {
     pub name: String,
     pub data: Vec<(f64, f64)>,
 }
 
+/// Vertical distance between stacked boolean traces
+pub const DIGITAL_SPACING: f64 = 1.5;
+
 impl Graph {
     pub fn from_default(
         name: String,
@@ -45,33 +69,46 @@ impl Graph {
             virt,
             zero: false,
             joinable: false,
+            missing: 0,
+            states: Option::None,
+            digital: false,
         }
     }
 
-    pub fn gen_highchart(&self) -> String {
-        let mut series_content = String::new();
+    /// Chart of this graph, its data is added to `store`
+    pub fn gen_highchart(&self, render: &RenderOptions, store: &mut DataStore) -> Result<String> {
+        if let Some(ref states) = self.states {
+            return self.gen_state_chart(states, store);
+        }
+        if self.digital {
+            return self.gen_digital_chart(store);
+        }
+
+        let mut series_content: Vec<serde_json::Value> = Vec::new();
+        // Every point of the decimated lines for zooming in
+        let mut full_content: Vec<Option<serde_json::Value>> = Vec::new();
         let mut min_y = 0f64;
         for s in &self.series {
-            let data = s
-                .data
-                .iter()
-                .map(|p| {
-                    let (x, y) = *p;
-                    format!("[{},{}]", x, y)
-                })
-                .collect::<Vec<String>>()
-                .join(",");
-
-            let series_text = format!(
-                "{{
-                name: '{name}',
-                data: [{data}]
-            }},",
-                name = s.name,
-                data = data
-            );
+            if s.data.is_empty() {
+                return Result::Err(Error::NoDataPoints);
+            }
 
-            series_content += &series_text;
+            let data = util::points(&s.data, 0f64);
+            let data = match render.max_points {
+                Some(max_points) if data.len() > max_points => {
+                    full_content.push(Option::Some(store.push_points(&data)));
+                    util::decimate(&data, max_points)
+                }
+                _ => {
+                    full_content.push(Option::None);
+                    data
+                }
+            };
+
+            series_content.push(json!({
+                "name": s.name,
+                "data": store.push_points(&data),
+            }));
 
             let min_y_local_option = s
                 .data
@@ -80,92 +117,475 @@ impl Graph {
                     let (_, y) = *p;
                     y
                 })
+                .filter(|y| !y.is_nan())
                 .min_by(|a, b| a.partial_cmp(b).unwrap_or(Equal));
 
-            let min_y_local = match min_y_local_option {
-                Some(value) => value,
-                None => {
-                    error!("No data points found in bag file! Robot code may have ended early.")
+            if let Some(min_y_local) = min_y_local_option {
+                if min_y_local < min_y {
+                    min_y = min_y_local;
                 }
-            };
-
-            if min_y_local < min_y {
-                min_y = min_y_local;
             }
         }
 
-        let unit = format!(" ({})", self.unit);
-
         let graph_type = if self.area { "area" } else { "line" };
 
-        let min_y_text = if self.zero {
+        let mut options = json!({
+            "chart": {
+                "type": graph_type,
+                "zoomType": "x"
+            },
+            "title": {
+                "text": format!("{} ({})", self.name_base, self.unit)
+            },
+            "subtitle": {
+                "text": self.subtitle()
+            },
+            "xAxis": {
+                "title": {
+                    "text": self.x_unit
+                }
+            },
+            "credits": {
+                "enabled": false
+            },
+            "series": series_content
+        });
+
+        if self.zero {
+            options["yAxis"] = json!({ "min": min_y });
+        }
+
+        let script = if full_content.iter().any(Option::is_some) {
+            let full = json!({
+                "target": render.max_points,
+                "series": full_content
+            });
             format!(
-                "yAxis: {{
-                min: {min_y}
-            }},",
-                min_y = min_y
+                "badlogFull['{id}'] = {full};
+        options.xAxis.events.afterSetExtremes = lodExtremes;",
+                id = self.element_id(),
+                full = util::script_json(&full)
             )
         } else {
             "".to_string()
         };
 
-        let (gen_l, gen_r) = if self.virt {
-            ("[ ".to_string(), " ]".to_string())
-        } else {
-            ("".to_string(), "".to_string())
+        Result::Ok(self.chart_html(400, &options, &script))
+    }
+
+    /// Graph name under the title, virtual graphs are marked with brackets
+    pub fn subtitle(&self) -> String {
+        let missing_text = match self.missing {
+            0 => "".to_string(),
+            1 => " - 1 missing sample".to_string(),
+            n => format!(" - {} missing samples", n),
         };
 
+        if self.virt {
+            format!("[ {} ]{}", self.name, missing_text)
+        } else {
+            format!("{}{}", self.name, missing_text)
+        }
+    }
+
+    /// Id of the chart's element, topic names can hold anything so they are hashed
+    pub fn element_id(&self) -> String {
+        format!("chart_{}", util::hash_string(&self.name))
+    }
+
+    /// Chart element and the script drawing it from `options`.
+    ///
+    /// Series data in `options` are references into the embedded data, the chart is drawn once
+    /// it is loaded. Functions can't be written as JSON, `script` can add them to `options`
+    /// before it is used.
+    fn chart_html(&self, height: usize, options: &serde_json::Value, script: &str) -> String {
         format!(
             r#"
-<div id="{name}" style="min-width: 310px; height: 400px; margin: 0 auto"></div>
+<div id="{id}" style="min-width: 310px; height: {height}px; margin: 0 auto"></div>
 <script>
-    Highcharts.chart('{name}', {{
-        chart: {{
-            type: '{graph_type}',
-            zoomType: 'x'
-        }},
-        title: {{
-            text: '{title}{unit}'
-        }},
-        subtitle: {{
-            text: '{generated_left}{name}{generated_right}'
-        }},
-        {min_y_text}
-        xAxis: {{
-            events: {{
-                setExtremes: syncExtremes
-            }},
-            title: {{
-                text: '{x_unit}'
-            }}
-        }},
-        credits: {{
-            enabled: false
-        }},
-        series: [{series_content}]
+    badlogCharts.push(function() {{
+        var options = {options};
+        Highcharts.each(options.series, function (series) {{
+            series.data = badlogPoints(series.data);
+        }});
+        options.xAxis.events = {{
+            setExtremes: syncExtremes
+        }};
+        {script}
+        Highcharts.chart('{id}', options);
     }});
 </script>
 "#,
-            name = self.name,
-            unit = unit,
-            title = self.name_base,
-            graph_type = graph_type,
-            min_y_text = min_y_text,
-            x_unit = self.x_unit,
-            series_content = series_content,
-            generated_left = gen_l,
-            generated_right = gen_r
+            id = self.element_id(),
+            height = height,
+            options = util::script_json(options),
+            script = script
         )
     }
 
-    pub fn gen_graphs(topics: &[Topic]) -> (Vec<Graph>, XAxis) {
-        let xaxis = XAxis::from(topics);
+    fn gen_state_chart(&self, states: &[String], store: &mut DataStore) -> Result<String> {
+        let mut series_content: Vec<serde_json::Value> = Vec::new();
+        let mut summary_rows = String::new();
+        let mut plot_bands: Vec<serde_json::Value> = Vec::new();
+
+        for s in &self.series {
+            if s.data.is_empty() {
+                return Result::Err(Error::NoDataPoints);
+            }
+
+            series_content.push(json!({
+                "name": s.name,
+                "data": store.push_points(&util::step_changes(&util::points(&s.data, 0f64))),
+            }));
+
+            let segments = util::state_segments(&s.data);
+
+            // Bands from several runs would cover each other, so only a lone series gets them
+            if self.series.len() == 1 {
+                for &(state, from, to) in &segments {
+                    plot_bands.push(json!({
+                        "from": from,
+                        "to": to,
+                        "colorIndex": state % 10
+                    }));
+                }
+            }
+
+            for (state, name) in states.iter().enumerate() {
+                let entries = segments.iter().filter(|seg| seg.0 == state).count();
+                if entries == 0 {
+                    continue;
+                }
+                let total: f64 = segments
+                    .iter()
+                    .filter(|seg| seg.0 == state)
+                    .map(|seg| seg.2 - seg.1)
+                    .sum();
+                let series_cell = if self.series.len() > 1 {
+                    format!("<td>{}</td>", util::escape_html(&s.name))
+                } else {
+                    "".to_string()
+                };
+                summary_rows += &format!(
+                    "<tr>{series}<td>{state}</td><td>{entries}</td><td>{total}</td></tr>\n",
+                    series = series_cell,
+                    state = util::escape_html(name),
+                    entries = entries,
+                    total = total
+                );
+            }
+        }
+
+        let series_head = if self.series.len() > 1 {
+            "<th>Series</th>"
+        } else {
+            ""
+        };
+
+        let options = json!({
+            "chart": {
+                "type": "line",
+                "zoomType": "x"
+            },
+            "title": {
+                "text": self.name_base
+            },
+            "subtitle": {
+                "text": self.subtitle()
+            },
+            "yAxis": {
+                "categories": states,
+                "title": {
+                    "text": null
+                }
+            },
+            "xAxis": {
+                "plotBands": plot_bands,
+                "title": {
+                    "text": self.x_unit
+                }
+            },
+            "plotOptions": {
+                "series": {
+                    "step": "left"
+                }
+            },
+            "credits": {
+                "enabled": false
+            },
+            "series": series_content
+        });
+
+        let script = "options.xAxis.plotBands.forEach(function(band) {
+            band.color = Highcharts.color(Highcharts.getOptions().colors[band.colorIndex]).setOpacity(0.3).get();
+        });";
+
+        Result::Ok(format!(
+            r#"{chart}<table class="table table-condensed"><thead><tr>{series_head}<th>State</th><th>Entries</th><th>Total</th></tr></thead><tbody>{summary_rows}</tbody></table>
+"#,
+            chart = self.chart_html(150 + 30 * states.len(), &options, script),
+            series_head = series_head,
+            summary_rows = summary_rows
+        ))
+    }
+
+    fn gen_digital_chart(&self, store: &mut DataStore) -> Result<String> {
+        let mut series_content: Vec<serde_json::Value> = Vec::new();
+        let mut summary_rows = String::new();
+        let mut ticks: Vec<f64> = Vec::new();
+        let mut labels = serde_json::Map::new();
+        let stacked = self.series.len() > 1;
+
+        for (i, s) in self.series.iter().enumerate() {
+            if s.data.is_empty() {
+                return Result::Err(Error::NoDataPoints);
+            }
+
+            // The first series is drawn on top
+            let offset = DIGITAL_SPACING * (self.series.len() - 1 - i) as f64;
+
+            series_content.push(json!({
+                "name": s.name,
+                "offset": offset,
+                "data": store.push_points(&util::step_changes(&util::points(&s.data, offset))),
+            }));
+
+            if stacked {
+                ticks.push(offset + 0.5);
+                labels.insert((offset + 0.5).to_string(), json!(s.name));
+            }
+
+            let (rising, falling) = util::digital_edges(&s.data);
+            let high: f64 = util::state_segments(&s.data)
+                .iter()
+                .filter(|seg| seg.0 == 1)
+                .map(|seg| seg.2 - seg.1)
+                .sum();
+            summary_rows += &format!(
+                "<tr><td>{series}</td><td>{rising}</td><td>{falling}</td><td>{high}</td></tr>\n",
+                series = util::escape_html(&s.name),
+                rising = rising,
+                falling = falling,
+                high = high
+            );
+        }
+
+        let (y_axis, labels) = if stacked {
+            // Tick positions have to be ascending
+            ticks.reverse();
+            (
+                json!({
+                    "tickPositions": ticks,
+                    "gridLineWidth": 0,
+                    "labels": {},
+                    "title": {
+                        "text": null
+                    }
+                }),
+                serde_json::Value::Object(labels),
+            )
+        } else {
+            (
+                json!({
+                    "tickPositions": [0, 1],
+                    "labels": {},
+                    "title": {
+                        "text": null
+                    }
+                }),
+                json!({ "0": "false", "1": "true" }),
+            )
+        };
+
+        let options = json!({
+            "chart": {
+                "type": "line",
+                "zoomType": "x"
+            },
+            "title": {
+                "text": self.name_base
+            },
+            "subtitle": {
+                "text": self.subtitle()
+            },
+            "yAxis": y_axis,
+            "xAxis": {
+                "title": {
+                    "text": self.x_unit
+                }
+            },
+            "tooltip": {},
+            "legend": {
+                "enabled": !stacked
+            },
+            "plotOptions": {
+                "series": {
+                    "step": "left"
+                }
+            },
+            "credits": {
+                "enabled": false
+            },
+            "series": series_content
+        });
+
+        let script = format!(
+            "var labels = {labels};
+        options.yAxis.labels.formatter = function() {{ return labels[this.value]; }};
+        options.tooltip.pointFormatter = function() {{
+            return this.series.name + ': <b>' + (this.y - this.series.options.offset ? 'true' : 'false') + '</b><br/>';
+        }};",
+            labels = util::script_json(&labels)
+        );
+
+        Result::Ok(format!(
+            r#"{chart}<table class="table table-condensed"><thead><tr><th>Series</th><th>Rising edges</th><th>Falling edges</th><th>Total true</th></tr></thead><tbody>{summary_rows}</tbody></table>
+"#,
+            chart = self.chart_html(150 + 40 * (self.series.len() - 1), &options, &script),
+            summary_rows = summary_rows
+        ))
+    }
+
+    /// Stacks the boolean graphs of every folder into one logic analyzer style graph.
+    ///
+    /// The stacked graph takes the place of the folder's first boolean graph.
+    pub fn stack_bools(graphs: Vec<Graph>) -> Vec<Graph> {
+        let stackable = |g: &Graph| g.digital && !g.virt && g.series.len() == 1;
+
+        let mut out: Vec<Graph> = Vec::with_capacity(graphs.len());
+        let mut stacks: Vec<(usize, usize)> = Vec::new();
+        for graph in graphs {
+            if !stackable(&graph) {
+                out.push(graph);
+                continue;
+            }
+            let stack = stacks.iter_mut().find(|s| {
+                out[s.0].name_folder.eq(&graph.name_folder) && out[s.0].x_unit.eq(&graph.x_unit)
+            });
+            match stack {
+                Some(stack) => {
+                    let stacked = &mut out[stack.0];
+                    let mut series = graph.series;
+                    series[0].name = graph.name_base;
+                    stacked.series.extend(series);
+                    stacked.missing += graph.missing;
+                    stack.1 += 1;
+                }
+                None => {
+                    stacks.push((out.len(), 1));
+                    out.push(graph);
+                }
+            }
+        }
+
+        for &(index, count) in &stacks {
+            if count < 2 {
+                continue;
+            }
+            let graph = &mut out[index];
+            let name = if graph.name_folder.is_empty() {
+                "Booleans".to_string()
+            } else {
+                format!("{}/Booleans", graph.name_folder)
+            };
+            let mut series = ::std::mem::take(&mut graph.series);
+            series[0].name = graph.name_base.clone();
+            let mut stacked = Graph::from_default(
+                name,
+                ::UNITLESS.to_string(),
+                graph.x_unit.clone(),
+                series,
+                true,
+            );
+            stacked.digital = true;
+            stacked.missing = graph.missing;
+            *graph = stacked;
+        }
+
+        out
+    }
+
+    pub fn gen_graphs(topics: &[Topic], diag: &mut Diagnostics) -> Result<(Vec<Graph>, XAxis)> {
+        let xaxis = match XAxis::try_from(topics) {
+            Ok(xaxis) => xaxis,
+            Err(e) => {
+                diag.error(e)?;
+                XAxis::index()
+            }
+        };
+
+        // Topics with their own time column get an axis per distinct column
+        let mut topic_axes: Vec<(String, XAxis)> = Vec::new();
+        for topic in topics {
+            if let Some(axis_name) = topic.xaxis_topic() {
+                if topic_axes.iter().any(|a| a.0.eq(axis_name)) {
+                    continue;
+                }
+                match topics.iter().find(|t| t.name.eq(axis_name)) {
+                    Some(axis_topic) => {
+                        topic_axes.push((axis_name.to_string(), XAxis::from_topic(axis_topic)))
+                    }
+                    None => diag.error(Error::MissingXaxis(axis_name.to_string()))?,
+                }
+            }
+        }
+
+        // Time columns only have samples in some rows, so they are drawn at those rows
+        let mut row_axes: Vec<(String, XAxis)> = Vec::new();
+        for topic in topics {
+            let is_time_column = topic_axes.iter().any(|a| a.0.eq(&topic.name));
+            let in_axis = match xaxis.data {
+                Some(ref data) => topic.rows.iter().all(|row| *row < data.len()),
+                None => true,
+            };
+            if is_time_column
+                && topic.xaxis_topic().is_none()
+                && !topic.attrs.contains(&Attribute::Xaxis)
+                && topic.rows.len() == topic.data.len()
+                && in_axis
+            {
+                row_axes.push((topic.name.clone(), xaxis.at_rows(&topic.rows)));
+            }
+        }
+
+        let axis_of = |topic: &Topic| -> Option<&XAxis> {
+            match topic.xaxis_topic() {
+                Some(axis_name) => topic_axes
+                    .iter()
+                    .find(|a| a.0.eq(axis_name))
+                    .map(|a| &a.1),
+                None => row_axes
+                    .iter()
+                    .find(|a| a.0.eq(&topic.name))
+                    .map_or(Some(&xaxis), |a| Some(&a.1)),
+            }
+        };
+
+        // Mismatched lengths can't be bound to an axis, so those topics are left out
+        let mut bound: Vec<(&Topic, &XAxis)> = Vec::with_capacity(topics.len());
+        for topic in topics {
+            let axis = match axis_of(topic) {
+                Some(axis) => axis,
+                None => continue,
+            };
+            if let Some(ref axis_data) = axis.data {
+                if !topic.is_hidden_only() && axis_data.len() != topic.data.len() {
+                    diag.error(Error::AxisLength {
+                        topic: topic.name.clone(),
+                        len: topic.data.len(),
+                        axis_len: axis_data.len(),
+                    })?;
+                    continue;
+                }
+            }
+            bound.push((topic, axis));
+        }
 
         let mut graphs: Vec<Graph> = Vec::new();
         // Scope to stop borrow of xaxis by gen_series
         {
-            let gen_series = |data: Vec<f64>, name: String| {
-                let data = if let Some(ref xaxis_data) = xaxis.data {
+            let gen_series = |axis: &XAxis, data: Vec<f64>, name: String| {
+                let data = if let Some(ref xaxis_data) = axis.data {
                     util::bind_axis(xaxis_data, &data)
                 } else {
                     util::fake_x_axis(&data)
@@ -173,15 +593,15 @@ impl Graph {
                 Series { name, data }
             };
 
-            for topic in topics {
+            for &(topic, axis) in &bound {
                 // Handle direct
                 if !topic.attrs.contains(&Attribute::Hide) {
-                    let series = gen_series(topic.data.clone(), topic.name_base.clone());
+                    let series = gen_series(axis, topic.data.clone(), topic.name_base.clone());
 
                     let mut graph = Graph::from_default(
                         topic.name.clone(),
                         topic.unit.clone(),
-                        xaxis.name.clone(),
+                        axis.name.clone(),
                         vec![series],
                         false,
                     );
@@ -190,9 +610,35 @@ impl Graph {
 
                     graph.zero = topic.attrs.contains(&Attribute::Zero);
 
+                    graph.missing = topic.missing;
+
+                    if topic.is_state() {
+                        graph.states = Option::Some(topic.states.clone());
+                    }
+
+                    graph.digital = topic.is_bool();
+
                     graphs.push(graph);
                 }
 
+                if topic.is_state() {
+                    if topic.attrs.iter().any(|attr| {
+                        matches!(
+                            *attr,
+                            Attribute::Delta
+                                | Attribute::Differentiate
+                                | Attribute::Integrate
+                                | Attribute::Join(_)
+                        )
+                    }) {
+                        diag.warn(format!(
+                            "Derived graphs and joins are ignored on state topic {}",
+                            topic.name
+                        ));
+                    }
+                    continue;
+                }
+
                 // Handle delta
                 {
                     if topic.attrs.contains(&Attribute::Delta) {
@@ -202,10 +648,10 @@ impl Graph {
 
                         let unit = topic.unit.clone();
 
-                        let series = gen_series(topic.data.clone(), name_base).delta();
+                        let series = gen_series(axis, topic.data.clone(), name_base).delta();
 
                         let graph =
-                            Graph::from_default(name, unit, xaxis.name.clone(), vec![series], true);
+                            Graph::from_default(name, unit, axis.name.clone(), vec![series], true);
 
                         graphs.push(graph);
                     }
@@ -218,12 +664,12 @@ impl Graph {
 
                         let (_, name_base) = util::split_name(&name);
 
-                        let unit = format!("{}/{}", topic.unit, xaxis.unit);
+                        let unit = format!("{}/{}", topic.unit, axis.unit);
 
-                        let series = gen_series(topic.data.clone(), name_base).differentiate();
+                        let series = gen_series(axis, topic.data.clone(), name_base).differentiate();
 
                         let graph =
-                            Graph::from_default(name, unit, xaxis.name.clone(), vec![series], true);
+                            Graph::from_default(name, unit, axis.name.clone(), vec![series], true);
 
                         graphs.push(graph);
                     }
@@ -236,13 +682,13 @@ impl Graph {
 
                         let (_, name_base) = util::split_name(&name);
 
-                        let unit = format!("{}*{}", topic.unit, xaxis.unit);
+                        let unit = format!("{}*{}", topic.unit, axis.unit);
 
                         let (series, _total_sum) =
-                            gen_series(topic.data.clone(), name_base).integrate();
+                            gen_series(axis, topic.data.clone(), name_base).integrate();
 
                         let graph =
-                            Graph::from_default(name, unit, xaxis.name.clone(), vec![series], true);
+                            Graph::from_default(name, unit, axis.name.clone(), vec![series], true);
 
                         graphs.push(graph);
                     }
@@ -250,7 +696,7 @@ impl Graph {
             }
 
             // Joins need to run after all direct graphs are added so an invalid join can be detected
-            for topic in topics {
+            for &(topic, axis) in bound.iter().filter(|b| !b.0.is_state()) {
                 // Handle join
                 for attr in &topic.attrs {
                     if let Attribute::Join(join_graph_name) = attr.clone() {
@@ -261,10 +707,8 @@ impl Graph {
                                 .last();
                             if let Some(join_graph) = join_graph {
                                 if !join_graph.joinable {
-                                    error!(
-                                        "Attempting to join to non-joinable graph {}",
-                                        join_graph.name
-                                    );
+                                    diag.error(Error::NonJoinableGraph(join_graph.name.clone()))?;
+                                    continue;
                                 }
 
                                 let join_graph: &mut Graph = join_graph;
@@ -276,24 +720,31 @@ impl Graph {
                                     .count()
                                     > 0
                                 {
-                                    warning!(
+                                    diag.warn(format!(
                                         "Attempting to join multiple topics with same name: {}",
                                         topic.name_base
-                                    );
+                                    ));
                                 }
 
                                 if join_graph.unit.ne(&topic.unit) {
-                                    warning!(
+                                    diag.warn(format!(
                                         "Attempting to join different units: {} ({}) and {} ({})",
                                         join_graph.name,
                                         join_graph.unit.clone(),
                                         topic.name,
                                         topic.unit.clone()
-                                    );
+                                    ));
+                                }
+
+                                if join_graph.x_unit.ne(&axis.name) {
+                                    diag.warn(format!(
+                                        "Joining topics with different x-axes into {}: {} and {}",
+                                        join_graph.name, join_graph.x_unit, axis.name
+                                    ));
                                 }
 
                                 let series =
-                                    gen_series(topic.data.clone(), topic.name_base.clone());
+                                    gen_series(axis, topic.data.clone(), topic.name_base.clone());
 
                                 join_graph.series.push(series);
 
@@ -301,11 +752,11 @@ impl Graph {
                             } else {
                                 let name = join_graph_name;
                                 let series =
-                                    gen_series(topic.data.clone(), topic.name_base.clone());
+                                    gen_series(axis, topic.data.clone(), topic.name_base.clone());
                                 let mut graph = Graph::from_default(
                                     name,
                                     topic.unit.clone(),
-                                    xaxis.name.clone(),
+                                    axis.name.clone(),
                                     vec![series],
                                     true,
                                 );
@@ -322,7 +773,9 @@ impl Graph {
             }
         }
 
-        (graphs, xaxis)
+        diag.check()?;
+
+        Result::Ok((graphs, xaxis))
     }
 }
 
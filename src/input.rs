@@ -1,17 +1,25 @@
 use attribute::Attribute;
+use config::Config;
 
 use csv;
+use error::{Diagnostics, Error, Result};
 use util;
 
+use wpilog::{self, WPILOG_MAGIC};
 use xaxis::XAxis;
-use Opt;
 
+use flate2::read::GzDecoder;
 use serde_json;
 use std::convert::From;
-use std::fs::{self, File};
-use std::io::prelude::*;
-use std::io::SeekFrom;
-use tempfile;
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Path that stands for stdin when reading and stdout when writing
+pub const STDIO_PATH: &str = "-";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct JSONTopic {
@@ -32,14 +40,6 @@ struct JSONHeader {
     pub values: Vec<JSONValue>,
 }
 
-#[derive(Debug)]
-struct MidLevelInput {
-    pub json_header: Option<JSONHeader>,
-    pub body: Vec<(String, Vec<String>)>,
-    pub json_header_text: Option<String>,
-    pub csv_text: String,
-}
-
 #[derive(Debug)]
 pub struct Topic {
     pub name: String,
@@ -47,26 +47,38 @@ pub struct Topic {
     pub name_folder: String,
     pub unit: String,
     pub attrs: Vec<Attribute>,
+    /// Samples, `NAN` marks a gap where nothing was logged
     pub data: Vec<f64>,
+    pub missing: usize,
+    /// Rows the samples of a topic with its own time column were logged in
+    pub rows: Vec<usize>,
+    /// Distinct values of a state topic, its samples are indices into this
+    pub states: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct Log {
     pub name: String,
     pub name_base: String,
     pub name_folder: String,
     pub unit: String,
+    #[serde(skip)]
     pub attrs: Vec<Attribute>,
+    #[serde(skip)]
     pub data: Vec<(u64, String)>,
     pub lines: Option<Vec<String>>,
+    /// Messages of `lines` at their position on the x-axis
+    pub entries: Option<Vec<(f64, String)>>,
+    pub run: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct Value {
     pub name: String,
     pub name_base: String,
     pub name_folder: String,
     pub value: String,
+    pub run: Option<String>,
 }
 
 #[derive(Debug)]
@@ -78,6 +90,24 @@ pub struct Input {
     pub csv_text: String,
 }
 
+/// How the text of an input is read, wpilog files are always recognised by their header
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum InputFormat {
+    /// Bag if the first line is a JSON object, otherwise CSV
+    #[default]
+    Auto,
+    Bag,
+    Csv,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    pub trim_doubles: bool,
+    pub format: InputFormat,
+    /// Extra units and attributes merged over the ones in the log
+    pub config: Option<Config>,
+}
+
 #[derive(Debug)]
 enum ParseMode {
     Bag(JSONHeader),
@@ -92,12 +122,23 @@ impl From<&JSONValue> for Value {
             name_base: base,
             name_folder: folder,
             value: value.value.clone(),
+            run: Option::None,
         }
     }
 }
 
-impl From<&JSONTopic> for Topic {
-    fn from(topic: &JSONTopic) -> Self {
+impl Value {
+    /// Reads a `<name>=<value>` command line option
+    pub fn from_option(option_text: &str) -> Result<Value> {
+        match util::split_option(option_text) {
+            Some((name, value)) => Result::Ok(Value::from(&JSONValue { name, value })),
+            None => Result::Err(Error::ValueOption(option_text.to_string())),
+        }
+    }
+}
+
+impl Topic {
+    fn from_json(topic: &JSONTopic, diag: &mut Diagnostics) -> Result<Self> {
         let (folder, base) = util::split_name(&topic.name);
         let unit = if topic.unit.is_empty() {
             ::UNITLESS.to_string()
@@ -105,16 +146,19 @@ impl From<&JSONTopic> for Topic {
             topic.unit.clone()
         };
 
-        let attrs: Vec<Attribute> = topic.get_attrs();
+        let attrs: Vec<Attribute> = topic.get_attrs(diag)?;
 
-        Topic {
+        Result::Ok(Topic {
             name: topic.name.clone(),
             name_base: base,
             name_folder: folder,
             unit,
             attrs,
             data: Vec::new(),
-        }
+            missing: 0,
+            rows: Vec::new(),
+            states: Vec::new(),
+        })
     }
 }
 
@@ -135,94 +179,157 @@ impl<'a> From<Topic> for Log {
             attrs: topic.attrs,
             data: Vec::new(),
             lines: Option::None,
+            entries: Option::None,
+            run: Option::None,
         }
     }
 }
 
-impl From<&(String, Vec<String>)> for Topic {
-    fn from(column: &(String, Vec<String>)) -> Self {
-        let (folder, base) = util::split_name(&column.0);
+impl From<&str> for Topic {
+    fn from(column: &str) -> Self {
+        let (folder, base) = util::split_name(column);
         let unit = ::UNITLESS.to_string();
 
         let attrs = Vec::<Attribute>::new();
 
         Topic {
-            name: column.0.clone(),
+            name: column.to_string(),
             name_base: base,
             name_folder: folder,
             unit,
             attrs,
             data: Vec::new(),
+            missing: 0,
+            rows: Vec::new(),
+            states: Vec::new(),
         }
     }
 }
 
-impl ParseMode {
-    fn get_header(&self) -> Option<&JSONHeader> {
-        match self {
-            ParseMode::Bag(ref header) => Option::Some(header),
-            ParseMode::Csv => Option::None,
+impl Topic {
+    pub fn is_hidden_only(&self) -> bool {
+        self.attrs.len() == 1 && self.attrs[0].eq(&Attribute::Hide)
+    }
+
+    /// Name of the topic holding this topic's own timestamps, if it has one
+    pub fn xaxis_topic(&self) -> Option<&str> {
+        self.attrs
+            .iter()
+            .filter_map(|attr| match *attr {
+                Attribute::XaxisTopic(ref name) => Some(name.as_str()),
+                _ => None,
+            })
+            .next()
+    }
+
+    fn push_missing(&mut self) {
+        self.data.push(f64::NAN);
+        self.missing += 1;
+    }
+
+    pub fn is_state(&self) -> bool {
+        self.attrs.contains(&Attribute::State)
+    }
+
+    pub fn is_bool(&self) -> bool {
+        self.attrs.contains(&Attribute::Bool)
+    }
+
+    fn push_state(&mut self, value: &str) {
+        let value = value.trim();
+        let index = match self.states.iter().position(|s| s.eq(value)) {
+            Some(index) => index,
+            None => {
+                self.states.push(value.to_string());
+                self.states.len() - 1
+            }
+        };
+        self.data.push(index as f64);
+    }
+
+    /// Turns a numeric topic that turned out to hold text into a state topic, `cells` are the
+    /// column's cells read so far
+    fn make_state(&mut self, cells: &[String]) {
+        self.data.clear();
+        self.missing = 0;
+        // `true` and `false` are just two more states once a column holds other text
+        self.attrs.retain(|attr| *attr != Attribute::Bool);
+        self.attrs.push(Attribute::State);
+        for cell in cells {
+            if cell.trim().is_empty() {
+                self.push_missing();
+            } else {
+                self.push_state(cell);
+            }
         }
     }
-}
 
-impl Topic {
-    fn fill(&mut self, data: &[String], trim_doubles: bool) {
-        if self.attrs.len() == 1 && self.attrs[0].eq(&Attribute::Hide) {
-            return;
-        }
-
-        for value in data {
-            let mut datapoint = value.to_string().parse::<f64>();
-            if datapoint.is_err() {
-                if trim_doubles {
-                    let test_value = value.trim();
-                    datapoint = test_value.to_string().parse::<f64>();
-                    if datapoint.is_err() {
-                        error!("Failed to parse \"{}\" as a double", value);
-                    }
+    fn push(&mut self, value: &str, trim_doubles: bool) -> Result<()> {
+        if self.is_state() {
+            self.push_state(value);
+            return Result::Ok(());
+        }
+
+        if is_bool_literal(value) {
+            self.data.push(if value.trim().eq_ignore_ascii_case("true") {
+                1f64
+            } else {
+                0f64
+            });
+            return Result::Ok(());
+        }
+
+        let mut datapoint = value.parse::<f64>();
+        if datapoint.is_err() && trim_doubles {
+            datapoint = value.trim().parse::<f64>();
+        }
+        match datapoint {
+            Ok(datapoint) => {
+                if self.is_bool() {
+                    self.data.push(if datapoint == 0f64 { 0f64 } else { 1f64 });
                 } else {
-                    error!(
-                        "Failed to parse \"{}\" as a double (maybe try --trim-doubles or hide topic)",
-                        value
-                    );
+                    self.data.push(datapoint);
                 }
+                Result::Ok(())
             }
-            let datapoint = datapoint.unwrap();
-            self.data.push(datapoint);
+            Err(_) => Result::Err(Error::ParseDouble {
+                value: value.to_string(),
+                trimmed: trim_doubles,
+            }),
         }
     }
 
-    fn is_log(&self) -> bool {
+    fn is_log(&self, diag: &mut Diagnostics) -> Result<bool> {
         if self.attrs.contains(&Attribute::Log) {
             if self.attrs.len() > 1 {
-                error!("Too many attributes on log topic {}", self.name)
+                diag.error(Error::LogAttributes(self.name.clone()))?;
             }
-            true
+            Result::Ok(true)
         } else {
-            false
+            Result::Ok(false)
         }
     }
 }
 
 impl Log {
-    fn fill(&mut self, data: &[String], trim_doubles: bool) {
-        for (i, value) in data.iter().enumerate() {
-            let trimmed_value = if trim_doubles { value.trim() } else { value };
+    fn push(&mut self, row: usize, value: &str, trim_doubles: bool) {
+        let trimmed_value = if trim_doubles { value.trim() } else { value };
 
-            if trimmed_value.parse::<f64>().is_err() {
-                self.data.push((i as u64, value.to_string()));
-            }
+        if trimmed_value.parse::<f64>().is_err() {
+            self.data.push((row as u64, value.to_string()));
         }
     }
 
     pub fn apply_xaxis(&mut self, xaxis: &XAxis) {
         let mut lines = Vec::with_capacity(self.data.len());
+        let mut entries = Vec::with_capacity(self.data.len());
         for line in &self.data {
             if line.1.is_empty() {
                 continue;
             }
 
+            entries.push((xaxis.at(line.0 as usize), line.1.clone()));
+
             if let Some(ref data) = xaxis.data {
                 lines.push(format!(
                     "[{} {}] {}",
@@ -233,215 +340,810 @@ impl Log {
             }
         }
         self.lines = Some(lines);
+        self.entries = Some(entries);
     }
 }
 
 impl JSONTopic {
-    fn get_attrs(&self) -> Vec<Attribute> {
+    /// Reads a CSV column header like `Drivetrain/Left Velocity (m/s) [differentiate,zero]`,
+    /// the unit and attributes are both optional
+    fn from_column(column: &str) -> Self {
+        let mut rest = column.trim();
+
         let mut attrs = Vec::new();
-        for attr_text in &self.attrs {
-            let attr = Attribute::from(&attr_text);
-            if attr.is_err() {
-                warning!("Failed to parse attribute {}, skipping it", attr_text);
-                continue;
+        if rest.ends_with(']') {
+            if let Some(open) = rest.rfind('[') {
+                attrs = rest[open + 1..rest.len() - 1]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|attr| !attr.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                rest = rest[..open].trim_end();
             }
-            let attr = attr.unwrap();
-            if attrs.contains(&attr) {
-                warning!(
-                    "Duplicate attribute \"{}\" on topic {}, ignoring duplicate",
-                    attr_text,
-                    self.name
-                );
-                continue;
+        }
+
+        let mut unit = String::new();
+        if rest.ends_with(')') {
+            if let Some(open) = rest.rfind('(') {
+                unit = rest[open + 1..rest.len() - 1].trim().to_string();
+                rest = rest[..open].trim_end();
             }
-            attrs.push(attr);
         }
-        attrs
+
+        // A header that is nothing but a unit or attributes is kept as the name
+        if rest.is_empty() {
+            return JSONTopic {
+                name: column.to_string(),
+                unit: String::new(),
+                attrs: Vec::new(),
+            };
+        }
+
+        JSONTopic {
+            name: rest.to_string(),
+            unit,
+            attrs,
+        }
+    }
+
+    fn get_attrs(&self, diag: &mut Diagnostics) -> Result<Vec<Attribute>> {
+        let mut attrs = Vec::new();
+        Attribute::merge(&mut attrs, &self.attrs, &self.name, diag)?;
+        Result::Ok(attrs)
     }
 }
 
 impl JSONHeader {
-    fn get_values(&self) -> Vec<Value> {
+    fn get_values(&self, diag: &mut Diagnostics) -> Result<Vec<Value>> {
         let mut values: Vec<Value> = Vec::new();
         for value in &self.values {
             if let Some(duplicate) = values.iter().find(|v| v.name.eq(&value.name)) {
                 if !duplicate.value.eq(&value.value) {
-                    error!("Duplicate value {} with different values", value.name);
+                    diag.error(Error::DuplicateValue(value.name.clone()))?;
                 } else {
-                    warning!("Duplicate value {}, ignoring duplicate", value.name);
-                    continue;
+                    diag.warn(format!(
+                        "Duplicate value {}, ignoring duplicate",
+                        value.name
+                    ));
                 }
+                continue;
             }
             values.push(Value::from(value));
         }
-        values
+        Result::Ok(values)
     }
 
-    fn get_stream_shells(&self) -> (Vec<Topic>, Vec<Log>) {
+    fn get_stream_shells(&self, diag: &mut Diagnostics) -> Result<Vec<Topic>> {
         let mut topics: Vec<Topic> = Vec::new();
-        let mut logs: Vec<Log> = Vec::new();
         for topic in &self.topics {
-            if topics.iter().filter(|g| g.name.eq(&topic.name)).count() > 0 {
-                error!("Duplicate topic entry in JSON header for {}", &topic.name);
+            if topics.iter().any(|g| g.name.eq(&topic.name)) {
+                diag.error(Error::DuplicateTopic(topic.name.clone()))?;
+                continue;
             }
 
-            let topic = Topic::from(topic);
-            if topic.is_log() {
-                logs.push(Log::from(topic));
-            } else {
-                topics.push(topic);
-            }
+            topics.push(Topic::from_json(topic, diag)?);
         }
 
-        (topics, logs)
+        Result::Ok(topics)
+    }
+}
+
+/// Moves the topics with the log attribute out into logs
+fn split_logs(shells: Vec<Topic>, diag: &mut Diagnostics) -> Result<(Vec<Topic>, Vec<Log>)> {
+    let mut topics: Vec<Topic> = Vec::new();
+    let mut logs: Vec<Log> = Vec::new();
+    for topic in shells {
+        if topic.is_log(diag)? {
+            logs.push(Log::from(topic));
+        } else {
+            topics.push(topic);
+        }
     }
+    Result::Ok((topics, logs))
 }
 
-pub fn column_wise_csv_parse(mut reader: csv::Reader<File>) -> Vec<(String, Vec<String>)> {
-    let csv_header = { reader.headers().unwrap().clone() };
+fn is_gzip(input_path: &str, bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+        || Path::new(input_path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
 
-    let mut output = Vec::with_capacity(csv_header.len());
+/// A bag starts with its JSON header, which a CSV header line never looks like
+fn starts_with_json(bytes: &[u8]) -> bool {
+    let bytes = if bytes.starts_with(UTF8_BOM) {
+        &bytes[UTF8_BOM.len()..]
+    } else {
+        bytes
+    };
+    bytes
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|b| *b == b'{')
+}
 
-    for i in 0..csv_header.len() {
-        let column: String = (&csv_header[i]).to_string();
-        output.push((column, Vec::new()));
+/// Name of the input with any `.gz` extension removed, used for the report title
+pub fn display_name(input_path: &str) -> &str {
+    if input_path.to_ascii_lowercase().ends_with(".gz") {
+        &input_path[..input_path.len() - 3]
+    } else {
+        input_path
     }
+}
 
-    for row in reader.records() {
-        let row = match row {
-            Err(e) => error!("{}", e.to_string()),
-            Ok(row) => row,
-        };
+/// Where the cells of a CSV column end up
+#[derive(Debug, Clone, Copy)]
+enum Sink {
+    Topic(usize),
+    /// Topic sampled at its own rate and the column of its time column, it has a sample in every
+    /// row its time column has a value in. A time column has one in every row it isn't empty.
+    SparseTopic(usize, Option<usize>),
+    Log(usize),
+    Skip,
+}
 
-        if row.len() != output.len() {
-            error!(
-                "Row length ({}) does not match CSV header length ({})",
-                row.len(),
-                output.len()
-            );
-        }
+fn is_bool_literal(value: &str) -> bool {
+    let value = value.trim();
+    value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false")
+}
+
+/// Cells of a column seen so far, to tell if it only holds boolean literals
+#[derive(Debug, Clone, Default)]
+struct Literals {
+    first: Option<String>,
+    numbers: bool,
+}
 
-        for i in 0..output.len() {
-            let column: &mut (String, Vec<String>) = &mut output[i];
-            column.1.push((&row[i]).to_string());
+impl Literals {
+    fn see(&mut self, value: &str) {
+        if !is_bool_literal(value) {
+            self.numbers = true;
+        } else if self.first.is_none() {
+            self.first = Option::Some(value.to_string());
         }
     }
+}
+
+/// Keeps a copy of everything read so the CSV can be embedded in the report
+struct TeeReader<R> {
+    inner: R,
+    copy: Vec<u8>,
+}
 
-    output
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.copy.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
 }
 
-fn parse_mid_input(input_path: &str, opt: &Opt) -> MidLevelInput {
-    let input_file_contents: String = match fs::read_to_string(input_path) {
-        Ok(contents) => contents,
-        Err(f) => error!("Failed to open file \"{}\": {}", input_path, f.to_string()),
+fn open_input(input_path: &str) -> Result<Box<dyn BufRead>> {
+    let io_error = |e| Error::Io(input_path.to_string(), e);
+
+    let mut reader: Box<dyn BufRead> = if input_path == STDIO_PATH {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(input_path).map_err(io_error)?))
     };
 
-    let (parse_mode, csv_text, json_header_text) = if opt.csv {
-        (ParseMode::Csv, input_file_contents, Option::None)
+    let gzip = is_gzip(input_path, reader.fill_buf().map_err(io_error)?);
+    if gzip {
+        Result::Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
     } else {
-        let mut parts: Vec<&str> = input_file_contents.split('\n').collect();
-
-        let json_header_text = parts.remove(0).to_string();
-        let csv_text = parts.join("\n");
-
-        let json_header = serde_json::from_str(&json_header_text);
-        match json_header {
-            Err(e) => error!(
-                "Failed to parse json header: {} (if its a CSV file use --csv)",
-                e.to_string()
-            ),
-            Ok(json_header) => (
-                ParseMode::Bag(json_header),
-                csv_text,
-                Option::Some(json_header_text),
-            ),
+        Result::Ok(reader)
+    }
+}
+
+fn read_json_header(input_path: &str, reader: &mut dyn BufRead) -> Result<(JSONHeader, String)> {
+    let mut json_header_text = String::new();
+    if let Err(e) = reader.read_line(&mut json_header_text) {
+        return Result::Err(Error::Io(input_path.to_string(), e));
+    }
+    while json_header_text.ends_with('\n') || json_header_text.ends_with('\r') {
+        json_header_text.pop();
+    }
+
+    let json_header = serde_json::from_str(&json_header_text)?;
+    Result::Ok((json_header, json_header_text))
+}
+
+/// Text of a column in the first `rows` rows of a CSV, short rows give empty cells
+fn column_cells(csv_bytes: &[u8], column: usize, rows: usize) -> Result<Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv_bytes);
+    let mut cells = Vec::with_capacity(rows);
+    for record in reader.records().take(rows) {
+        cells.push(record?.get(column).unwrap_or("").to_string());
+    }
+    Result::Ok(cells)
+}
+
+/// Matches every stream to exactly one CSV column, leaving unused columns skipped
+fn bind_columns(
+    csv_header: &csv::StringRecord,
+    topics: &[Topic],
+    logs: &[Log],
+    diag: &mut Diagnostics,
+) -> Result<Vec<Sink>> {
+    let mut sinks = vec![Sink::Skip; csv_header.len()];
+
+    let is_time_column = |topic: &Topic| {
+        topics
+            .iter()
+            .any(|t| t.xaxis_topic().is_some_and(|name| name.eq(&topic.name)))
+    };
+    let is_sparse = |topic: &Topic| topic.xaxis_topic().is_some() || is_time_column(topic);
+    let time_column = |topic: &Topic| {
+        topic
+            .xaxis_topic()
+            .and_then(|name| csv_header.iter().position(|column| column.eq(name)))
+    };
+
+    let names = topics
+        .iter()
+        .enumerate()
+        .filter(|(_, topic)| !topic.is_hidden_only() || is_time_column(topic))
+        .map(|(i, topic)| {
+            if is_sparse(topic) {
+                (Sink::SparseTopic(i, time_column(topic)), &topic.name)
+            } else {
+                (Sink::Topic(i), &topic.name)
+            }
+        })
+        .chain(
+            logs.iter()
+                .enumerate()
+                .map(|(i, log)| (Sink::Log(i), &log.name)),
+        );
+
+    for (sink, name) in names {
+        let mut columns = csv_header
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.eq(name))
+            .map(|(i, _)| i);
+        match (columns.next(), columns.next()) {
+            (None, _) => diag.error(Error::MissingColumn(name.clone()))?,
+            (Some(column), None) => sinks[column] = sink,
+            (Some(_), Some(_)) => diag.error(Error::DuplicateColumn(name.clone()))?,
         }
+    }
+
+    Result::Ok(sinks)
+}
+
+pub fn parse_input(
+    input_path: &str,
+    options: &ParseOptions,
+    diag: &mut Diagnostics,
+) -> Result<Input> {
+    let mut reader = open_input(input_path)?;
+
+    let (is_wpilog, is_json) = match reader.fill_buf() {
+        Ok(bytes) => (bytes.starts_with(WPILOG_MAGIC), starts_with_json(bytes)),
+        Err(e) => return Result::Err(Error::Io(input_path.to_string(), e)),
     };
+    if is_wpilog {
+        diag.note(format!("Reading {} as a wpilog file", input_path));
+        let mut input = wpilog::parse_wpilog(reader, input_path, diag)?;
+        if let Some(ref config) = options.config {
+            config.apply(&mut input.topics, diag)?;
+            // The config may have turned topics into logs, which only keep their text
+            let shells = ::std::mem::take(&mut input.topics);
+            let (topics, logs) = split_logs(shells, diag)?;
+            input.topics = topics;
+            input.logs.extend(logs);
+        }
+        return Result::Ok(input);
+    }
+
+    let is_csv = match options.format {
+        InputFormat::Auto => !is_json,
+        InputFormat::Bag => false,
+        InputFormat::Csv => true,
+    };
+    diag.note(format!(
+        "Reading {} as a {} file{}",
+        input_path,
+        if is_csv { "CSV" } else { "bag" },
+        if options.format == InputFormat::Auto {
+            " (detected)"
+        } else {
+            ""
+        }
+    ));
 
-    let csv_reader: csv::Reader<File> = if opt.csv {
-        csv::Reader::from_path(input_path).unwrap()
+    let (parse_mode, json_header_text) = if is_csv {
+        (ParseMode::Csv, Option::None)
     } else {
-        let mut tempfile = tempfile::tempfile().unwrap();
-        tempfile.write_all(csv_text.as_bytes()).unwrap();
-        tempfile.seek(SeekFrom::Start(0)).unwrap();
-        csv::Reader::from_reader(tempfile)
+        let (json_header, json_header_text) = read_json_header(input_path, &mut reader)?;
+        (ParseMode::Bag(json_header), Option::Some(json_header_text))
     };
 
-    MidLevelInput {
-        json_header: parse_mode.get_header().cloned(),
-        body: column_wise_csv_parse(csv_reader),
-        json_header_text,
-        csv_text,
+    // Short rows are allowed, their missing cells become gaps
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(TeeReader {
+            inner: reader,
+            copy: Vec::new(),
+        });
+    let csv_header = csv_reader.headers()?.clone();
+
+    // Columns are bound by topic name, which CSV headers may follow with a unit and attributes
+    let (column_names, mut shells, values) = match parse_mode {
+        ParseMode::Bag(ref json_header) => (
+            csv_header,
+            json_header.get_stream_shells(diag)?,
+            json_header.get_values(diag)?,
+        ),
+        ParseMode::Csv => {
+            let columns: Vec<JSONTopic> = csv_header.iter().map(JSONTopic::from_column).collect();
+            let mut shells = Vec::with_capacity(columns.len());
+            for column in &columns {
+                shells.push(Topic::from_json(column, diag)?);
+            }
+            let column_names = columns.iter().map(|c| c.name.as_str()).collect();
+            (column_names, shells, Vec::new())
+        }
+    };
+
+    if let Some(ref config) = options.config {
+        config.apply(&mut shells, diag)?;
     }
-}
 
-pub fn parse_input(input_path: &str, opt: &Opt) -> Input {
-    let mid_input = parse_mid_input(input_path, opt);
+    let (mut topics, mut logs) = split_logs(shells, diag)?;
 
-    let (values, topics, logs) = if let Some(ref json_header) = mid_input.json_header {
-        let (mut empty_topics, mut empty_logs) = json_header.get_stream_shells();
-        for empty_topic in &mut empty_topics {
-            match mid_input
-                .body
-                .iter()
-                .filter(|x| (&(x.0)).eq(&(empty_topic.name)))
-                .count()
-            {
-                0 => error!("Can't find topic \"{}\" in CSV", empty_topic.name),
-                1 => {
-                    let data = &mid_input
-                        .body
-                        .iter()
-                        .filter(|x| (&(x.0)).eq(&(empty_topic.name)))
-                        .last()
-                        .unwrap()
-                        .1;
-                    empty_topic.fill(data, opt.trim_doubles);
+    let mut sinks = bind_columns(&column_names, &topics, &logs, diag)?;
+
+    let mut literals = vec![Literals::default(); topics.len()];
+    let mut row = csv::StringRecord::new();
+    let mut row_index = 0;
+    while csv_reader.read_record(&mut row)? {
+        if row.len() > sinks.len() {
+            return Result::Err(Error::RowLength {
+                row: row.len(),
+                header: sinks.len(),
+            });
+        }
+
+        for (column, sink) in sinks.iter_mut().enumerate() {
+            let value = row.get(column).unwrap_or("");
+            match *sink {
+                Sink::Topic(i) => {
+                    if value.trim().is_empty() {
+                        topics[i].push_missing();
+                        continue;
+                    }
+                    literals[i].see(value);
+                    if let Err(e) = topics[i].push(value, options.trim_doubles) {
+                        let is_text = value.trim().parse::<f64>().is_err();
+                        if let (ParseMode::Csv, true) = (&parse_mode, is_text) {
+                            // Plain CSV has no attributes, so text columns are taken as states
+                            let cells =
+                                column_cells(&csv_reader.get_ref().copy, column, row_index)?;
+                            topics[i].make_state(&cells);
+                            topics[i].push_state(value);
+                            continue;
+                        }
+                        // The rest of the column is skipped, one bad cell is enough to report
+                        diag.error(e)?;
+                        *sink = Sink::Skip;
+                    }
                 }
-                _ => error!("Multiple columns \"{}\" found in CSV", empty_topic.name),
+                Sink::SparseTopic(i, time) => {
+                    let timed = match time {
+                        Some(time) => !row.get(time).unwrap_or("").trim().is_empty(),
+                        None => !value.trim().is_empty(),
+                    };
+                    if !timed {
+                        continue;
+                    }
+                    if value.trim().is_empty() {
+                        topics[i].push_missing();
+                        topics[i].rows.push(row_index);
+                        continue;
+                    }
+                    literals[i].see(value);
+                    if let Err(e) = topics[i].push(value, options.trim_doubles) {
+                        diag.error(e)?;
+                        *sink = Sink::Skip;
+                        continue;
+                    }
+                    topics[i].rows.push(row_index);
+                }
+                Sink::Log(i) => logs[i].push(row_index, value, options.trim_doubles),
+                Sink::Skip => (),
             }
         }
+        row_index += 1;
+    }
 
-        for empty_log in &mut empty_logs {
-            match mid_input
-                .body
-                .iter()
-                .filter(|x| (&(x.0)).eq(&(empty_log.name)))
-                .count()
-            {
-                0 => error!("Can't find topic \"{}\" in CSV", empty_log.name),
-                1 => {
-                    let data = &mid_input
-                        .body
-                        .iter()
-                        .filter(|x| (&(x.0)).eq(&(empty_log.name)))
-                        .last()
-                        .unwrap()
-                        .1;
-                    empty_log.fill(data, opt.trim_doubles);
-                }
-                _ => error!("Multiple columns \"{}\" found in CSV", empty_log.name),
+    // Boolean literals make a topic boolean even without the attribute, if every cell is one
+    for (topic, literals) in topics.iter_mut().zip(&literals) {
+        if let Some(ref literal) = literals.first {
+            if topic.is_state() || topic.is_bool() {
+                continue;
+            }
+            if literals.numbers {
+                diag.error(Error::ParseDouble {
+                    value: literal.clone(),
+                    trimmed: options.trim_doubles,
+                })?;
+            } else {
+                topic.attrs.push(Attribute::Bool);
             }
         }
-        (json_header.get_values(), empty_topics, empty_logs)
-    } else {
-        let topics: Vec<Topic> = mid_input
-            .body
-            .iter()
-            .map(|x| {
-                let mut topic = Topic::from(x);
-                topic.fill(&x.1, opt.trim_doubles);
-                topic
-            })
-            .collect();
-        (Vec::new(), topics, Vec::new())
+    }
+
+    for topic in &topics {
+        if topic.missing > 0 {
+            diag.warn(format!(
+                "Topic {} is missing {} of {} samples, drawing them as gaps",
+                topic.name,
+                topic.missing,
+                topic.data.len()
+            ));
+        }
+    }
+
+    diag.check()?;
+
+    let csv_text = match String::from_utf8(csv_reader.into_inner().copy) {
+        Ok(csv_text) => csv_text,
+        Err(e) => {
+            return Result::Err(Error::Io(
+                input_path.to_string(),
+                io::Error::new(io::ErrorKind::InvalidData, e),
+            ))
+        }
     };
 
-    Input {
+    Result::Ok(Input {
         topics,
         logs,
         values,
-        json_header_text: mid_input.json_header_text,
-        csv_text: mid_input.csv_text,
+        json_header_text,
+        csv_text,
+    })
+}
+
+/// Writes a bag file: registered topics and values go into the JSON header line, which is
+/// written along with the CSV column names before the first row.
+pub struct BagWriter<W: Write> {
+    header: JSONHeader,
+    /// Output until the header is written, after that it is inside `csv`
+    out: Option<W>,
+    csv: Option<csv::Writer<W>>,
+}
+
+impl<W: Write> BagWriter<W> {
+    pub fn new(out: W) -> Self {
+        BagWriter {
+            header: JSONHeader {
+                topics: Vec::new(),
+                values: Vec::new(),
+            },
+            out: Option::Some(out),
+            csv: Option::None,
+        }
+    }
+
+    /// Adds a topic as the next column. Attributes are checked the way they are when the bag is
+    /// read, so an unknown one is an error here instead.
+    pub fn add_topic(&mut self, name: &str, unit: &str, attrs: &[&str]) -> Result<()> {
+        if self.csv.is_some() {
+            return Result::Err(Error::BagStarted(name.to_string()));
+        }
+        if self.header.topics.iter().any(|t| t.name.eq(name)) {
+            return Result::Err(Error::DuplicateTopic(name.to_string()));
+        }
+
+        let mut parsed: Vec<Attribute> = Vec::new();
+        for attr in attrs {
+            let attr = Attribute::from(attr)?;
+            if !parsed.contains(&attr) {
+                parsed.push(attr);
+            }
+        }
+        if parsed.contains(&Attribute::Log) && parsed.len() > 1 {
+            return Result::Err(Error::LogAttributes(name.to_string()));
+        }
+
+        self.header.topics.push(JSONTopic {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            attrs: parsed.iter().map(Attribute::to_string).collect(),
+        });
+        Result::Ok(())
+    }
+
+    pub fn add_value(&mut self, name: &str, value: &str) -> Result<()> {
+        if self.csv.is_some() {
+            return Result::Err(Error::BagStarted(name.to_string()));
+        }
+        if self.header.values.iter().any(|v| v.name.eq(name)) {
+            return Result::Err(Error::DuplicateValue(name.to_string()));
+        }
+
+        self.header.values.push(JSONValue {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+        Result::Ok(())
+    }
+
+    fn start(&mut self) -> Result<&mut csv::Writer<W>> {
+        if let Some(mut out) = self.out.take() {
+            let header = match serde_json::to_string(&self.header) {
+                Ok(header) => header,
+                Err(e) => return Result::Err(Error::JsonWrite(e)),
+            };
+            if let Err(e) = writeln!(out, "{}", header) {
+                return Result::Err(Error::BagWrite(e));
+            }
+            let mut csv = csv::WriterBuilder::new().flexible(true).from_writer(out);
+            csv.write_record(self.header.topics.iter().map(|t| t.name.as_str()))?;
+            self.csv = Option::Some(csv);
+        }
+        Result::Ok(self.csv.as_mut().unwrap())
+    }
+
+    /// Writes the cells of a row in the order the topics were added. An empty cell is a gap and
+    /// a short row leaves its last topics out.
+    pub fn write_row<I, T>(&mut self, row: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let header = self.header.topics.len();
+        let row: Vec<T> = row.into_iter().collect();
+        if row.len() > header {
+            return Result::Err(Error::RowLength {
+                row: row.len(),
+                header,
+            });
+        }
+        self.start()?.write_record(row)?;
+        Result::Ok(())
+    }
+
+    /// Writes the header if no row was, and returns the output
+    pub fn finish(mut self) -> Result<W> {
+        self.start()?;
+        match self.csv.take().unwrap().into_inner() {
+            Ok(out) => Result::Ok(out),
+            Err(e) => Result::Err(Error::BagWrite(e.into_error())),
+        }
+    }
+}
+
+impl Input {
+    /// Bag file holding this input's CSV, with the units and attributes of its topics and its
+    /// values in the JSON header. Reading it back gives the same topics, logs and values.
+    pub fn to_bag(&self) -> Result<String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(self.csv_text.as_bytes());
+        let mut bag = BagWriter::new(Vec::new());
+
+        // Columns keep their order, and a bag's column headers are only the topic names
+        for column in reader.headers()?.iter() {
+            let name = JSONTopic::from_column(column).name;
+            let stream = self
+                .topics
+                .iter()
+                .map(|t| (&t.name, &t.unit, &t.attrs))
+                .chain(self.logs.iter().map(|l| (&l.name, &l.unit, &l.attrs)))
+                .find(|stream| stream.0.eq(&name));
+            let (unit, attrs): (&str, Vec<String>) = match stream {
+                Some((_, unit, attrs)) => (unit, attrs.iter().map(Attribute::to_string).collect()),
+                None => (::UNITLESS, Vec::new()),
+            };
+            let attrs: Vec<&str> = attrs.iter().map(String::as_str).collect();
+            bag.add_topic(&name, unit, &attrs)?;
+        }
+        for value in &self.values {
+            bag.add_value(&value.name, &value.value)?;
+        }
+
+        for record in reader.records() {
+            bag.write_row(&record?)?;
+        }
+
+        Result::Ok(String::from_utf8_lossy(&bag.finish()?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    use graph::Graph;
+
+    /// Writes `content` to a file of its own in the temp folder
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path =
+            ::std::env::temp_dir().join(format!("badlogvis-{}-{}", ::std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn parse(path: &Path, format: InputFormat) -> Input {
+        let options = ParseOptions {
+            trim_doubles: false,
+            format,
+            config: Option::None,
+        };
+        let input = parse_input(path.to_str().unwrap(), &options, &mut Diagnostics::new());
+        fs::remove_file(path).unwrap();
+        input.unwrap()
+    }
+
+    /// Samples with gaps as `None`, which unlike `NAN` compare equal
+    fn samples(topic: &Topic) -> Vec<Option<f64>> {
+        topic
+            .data
+            .iter()
+            .map(|v| if v.is_nan() { None } else { Some(*v) })
+            .collect()
+    }
+
+    fn assert_same(a: &Input, b: &Input) {
+        assert_eq!(a.topics.len(), b.topics.len());
+        for (a, b) in a.topics.iter().zip(&b.topics) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.unit, b.unit);
+            assert_eq!(a.attrs, b.attrs);
+            assert_eq!(samples(a), samples(b));
+            assert_eq!(a.missing, b.missing);
+            assert_eq!(a.states, b.states);
+        }
+        assert_eq!(a.logs.len(), b.logs.len());
+        for (a, b) in a.logs.iter().zip(&b.logs) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.data, b.data);
+        }
+        let values = |input: &Input| -> Vec<(String, String)> {
+            input
+                .values
+                .iter()
+                .map(|v| (v.name.clone(), v.value.clone()))
+                .collect()
+        };
+        assert_eq!(values(a), values(b));
+    }
+
+    #[test]
+    fn bag_writer_output_reads_back() {
+        let mut bag = BagWriter::new(Vec::new());
+        bag.add_topic("Time", "s", &["xaxis"]).unwrap();
+        bag.add_topic("Drivetrain/Left, Velocity", "m/s", &["differentiate"])
+            .unwrap();
+        bag.add_topic("Arm/Mode", "", &["state"]).unwrap();
+        bag.add_topic("Events", "", &["log"]).unwrap();
+        bag.add_value("Robot", "Comp \"bot\", 2").unwrap();
+
+        match bag.add_topic("Arm/Speed", "", &["integrate", "sideways"]) {
+            Err(Error::UnknownAttribute(ref text)) => assert_eq!(text, "sideways"),
+            other => panic!("expected an attribute error, got {:?}", other),
+        }
+
+        bag.write_row(&["0", "1.5", "IDLE", "hello, world"])
+            .unwrap();
+        // Short rows leave gaps
+        bag.write_row(&["1", "2.5", "MOVING"]).unwrap();
+        bag.write_row(&["2", "", "IDLE", "bye \"now\""]).unwrap();
+
+        match bag.write_row(&["3", "1", "IDLE", "", "extra"]) {
+            Err(Error::RowLength { row, header }) => assert_eq!((row, header), (5, 4)),
+            other => panic!("expected a row length error, got {:?}", other),
+        }
+        match bag.add_topic("Late", "", &[]) {
+            Err(Error::BagStarted(ref name)) => assert_eq!(name, "Late"),
+            other => panic!("expected a started bag error, got {:?}", other),
+        }
+
+        let path = temp_file("writer.bag", &bag.finish().unwrap());
+        let input = parse(&path, InputFormat::Auto);
+
+        assert_eq!(input.topics.len(), 3);
+        let time = &input.topics[0];
+        assert_eq!((time.name.as_str(), time.unit.as_str()), ("Time", "s"));
+        assert_eq!(time.attrs, vec![Attribute::Xaxis]);
+        assert_eq!(samples(time), vec![Some(0.0), Some(1.0), Some(2.0)]);
+
+        let velocity = &input.topics[1];
+        assert_eq!(velocity.name, "Drivetrain/Left, Velocity");
+        assert_eq!(velocity.unit, "m/s");
+        assert_eq!(velocity.attrs, vec![Attribute::Differentiate]);
+        assert_eq!(samples(velocity), vec![Some(1.5), Some(2.5), None]);
+        assert_eq!(velocity.missing, 1);
+
+        let mode = &input.topics[2];
+        assert_eq!(mode.states, vec!["IDLE", "MOVING"]);
+        assert_eq!(samples(mode), vec![Some(0.0), Some(1.0), Some(0.0)]);
+
+        assert_eq!(input.logs.len(), 1);
+        let lines: Vec<&(u64, String)> = input.logs[0]
+            .data
+            .iter()
+            .filter(|line| !line.1.is_empty())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                &(0, "hello, world".to_string()),
+                &(2, "bye \"now\"".to_string())
+            ]
+        );
+
+        assert_eq!(input.values.len(), 1);
+        assert_eq!(input.values[0].name, "Robot");
+        assert_eq!(input.values[0].value, "Comp \"bot\", 2");
+    }
+
+    #[test]
+    fn csv_converted_to_bag_reads_the_same() {
+        let csv_text = "Time (s) [xaxis],\"Shooter/RPM, Left (rpm) [zero,differentiate]\",\
+                        Mode,Events [log],Enabled\n\
+                        0,100,IDLE,start,true\n\
+                        1,,\"SPIN, UP\",,false\n\
+                        2,300,IDLE,\"done, \"\"ok\"\"\",true\n\
+                        3,400\n";
+        let csv_path = temp_file("convert.csv", csv_text.as_bytes());
+        let mut input = parse(&csv_path, InputFormat::Csv);
+        input
+            .values
+            .push(Value::from_option("Bench=Rig 2").unwrap());
+
+        let bag_path = temp_file("convert.bag", input.to_bag().unwrap().as_bytes());
+        let bag = parse(&bag_path, InputFormat::Bag);
+
+        assert_eq!(bag.topics.len(), 4);
+        assert_eq!(bag.topics[2].states, vec!["IDLE", "SPIN, UP"]);
+        assert!(bag.topics[3].is_bool());
+        assert_same(&input, &bag);
+    }
+
+    /// Points of the vision graphs of a log with a vision time column
+    fn vision_graphs(time_attrs: &str) -> (Vec<(f64, f64)>, Vec<(f64, f64)>) {
+        let bag = format!(
+            "{{\"topics\":[\
+             {{\"name\":\"Time\",\"unit\":\"s\",\"attrs\":[{}]}},\
+             {{\"name\":\"Vision/Time\",\"unit\":\"s\",\"attrs\":[]}},\
+             {{\"name\":\"Vision/X\",\"unit\":\"m\",\"attrs\":[\"xaxis:Vision/Time\"]}}],\
+             \"values\":[]}}\n\
+             Time,Vision/Time,Vision/X\n\
+             10,,\n11,0.9,5\n12,,\n13,2.8,6\n",
+            time_attrs
+        );
+        let path = temp_file(&format!("vision{}.bag", time_attrs.len()), bag.as_bytes());
+        let input = parse(&path, InputFormat::Bag);
+        let (graphs, _) = Graph::gen_graphs(&input.topics, &mut Diagnostics::new()).unwrap();
+        let points = |name: &str| {
+            let graph = graphs.iter().find(|g| g.name.eq(name)).unwrap();
+            graph.series[0].data.clone()
+        };
+        (points("Vision/Time"), points("Vision/X"))
+    }
+
+    #[test]
+    fn time_column_is_drawn_at_its_rows_on_the_xaxis() {
+        let (time, x) = vision_graphs("\"xaxis\"");
+        assert_eq!(time, vec![(11.0, 0.9), (13.0, 2.8)]);
+        assert_eq!(x, vec![(0.9, 5.0), (2.8, 6.0)]);
+    }
+
+    #[test]
+    fn time_column_is_drawn_at_its_rows_without_an_xaxis() {
+        let (time, x) = vision_graphs("");
+        assert_eq!(time, vec![(1.0, 0.9), (3.0, 2.8)]);
+        assert_eq!(x, vec![(0.9, 5.0), (2.8, 6.0)]);
     }
 }
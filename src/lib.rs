@@ -0,0 +1,405 @@
+extern crate base64;
+extern crate csv;
+extern crate flate2;
+extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate sha1;
+
+use std::fs;
+use std::path::Path;
+
+mod util;
+pub mod attached_file;
+pub mod attribute;
+pub mod compare;
+pub mod config;
+pub mod embed;
+pub mod error;
+pub mod export;
+pub mod filter;
+pub mod folder;
+pub mod graph;
+pub mod input;
+pub mod markdown;
+pub mod svg;
+pub mod window;
+pub mod wpilog;
+pub mod xaxis;
+
+pub use attached_file::AttachedFile;
+pub use config::Config;
+pub use embed::DataStore;
+pub use error::{Diagnostics, Error, Result};
+pub use folder::Folder;
+pub use graph::{Graph, RenderOptions};
+pub use input::{parse_input, BagWriter, Input, InputFormat, ParseOptions};
+
+pub const UNITLESS: &str = "ul";
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Kind of report written
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    Html,
+    /// Folder of SVG files with an index page
+    Svg,
+    /// Markdown file with its graphs as SVG files in an assets folder
+    Markdown,
+    /// Processed folders, graphs and logs for other tools to read
+    Json,
+}
+
+impl OutputFormat {
+    pub fn from(format_text: &str) -> Result<OutputFormat> {
+        if format_text.eq("html") {
+            return Result::Ok(OutputFormat::Html);
+        }
+        if format_text.eq("svg") {
+            return Result::Ok(OutputFormat::Svg);
+        }
+        if format_text.eq("markdown") {
+            return Result::Ok(OutputFormat::Markdown);
+        }
+        if format_text.eq("json") {
+            return Result::Ok(OutputFormat::Json);
+        }
+
+        Result::Err(Error::UnknownFormat(format_text.to_string()))
+    }
+
+    /// Output path used when none is given, next to the input
+    pub fn default_output(self, input_path: &str) -> String {
+        match self {
+            OutputFormat::Html => format!("{}.html", input_path),
+            OutputFormat::Svg => format!("{}_svg", input_path),
+            OutputFormat::Markdown => format!("{}.md", input_path),
+            OutputFormat::Json => format!("{}.json", input_path),
+        }
+    }
+
+    /// Whether the report can be written as one stream to stdout
+    pub fn is_single_file(self) -> bool {
+        self == OutputFormat::Html || self == OutputFormat::Json
+    }
+}
+
+/// Writes `content` to `path`, creating the folders it is in
+pub fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            return Result::Err(Error::Write(parent.display().to_string(), e));
+        }
+    }
+    match fs::write(path, content) {
+        Ok(_) => Result::Ok(()),
+        Err(e) => Result::Err(Error::Write(path.display().to_string(), e)),
+    }
+}
+
+/// Optional parts of an HTML report's header, everything is left out by default
+#[derive(Default)]
+pub struct HtmlHeader<'a> {
+    /// CSV with the derived and joined topics, offered as a second download
+    pub augmented_csv: Option<&'a str>,
+    /// JSON header of a bag input
+    pub json_header: Option<&'a str>,
+    /// Description of the window the log was cropped to
+    pub window: Option<&'a str>,
+    pub attatched_files: Vec<AttachedFile>,
+}
+
+pub fn gen_html(
+    input: &str,
+    folders: Vec<Folder>,
+    csv_text: &str,
+    header: HtmlHeader,
+    render: &RenderOptions,
+) -> Result<String> {
+    let bootstrap_css_source = include_str!("web_res/bootstrap.min.css");
+    let jquery_js_source = include_str!("web_res/jquery-3.2.1.min.js");
+    let bootstrap_js_source = include_str!("web_res/bootstrap.min.js");
+    let highcharts_js_source = include_str!("web_res/highcharts.js");
+    let highcharts_boost_js_source = include_str!("web_res/boost.js");
+    let highcharts_exporting_js_source = include_str!("web_res/exporting.js");
+    let highcharts_offline_exporting_source = include_str!("web_res/offline-exporting.js");
+
+    let csv_filename = util::escape_html(&format!("{}.csv", input));
+
+    let augmented_button = if header.augmented_csv.is_some() {
+        format!(
+            r#" <a id="badlog_augmented_csv" download="{}" class="btn btn-default btn-md">Download CSV with derived topics</a>"#,
+            util::escape_html(&format!("{} derived.csv", input))
+        )
+    } else {
+        "".to_string()
+    };
+
+    let mut store = DataStore::new(render.float32);
+    let mut content = String::new();
+
+    for folder in folders {
+        content += &folder.gen_html(render, &mut store)?;
+    }
+
+    let (data_base64, data_sections) = store.encode(csv_text, header.augmented_csv, render.compress);
+
+    let attatched_file_text = if header.attatched_files.is_empty() {
+        "".to_string()
+    } else {
+        let mut files = "".to_string();
+        for file in &header.attatched_files {
+            files += &file.get_button_html();
+        }
+
+        format!("<br />\n{}", files)
+    };
+
+    let window = if let Some(window) = header.window {
+        format!(r#"<p class="text-muted">Cropped to {}</p>"#, util::escape_html(window))
+    } else {
+        "".to_string()
+    };
+
+    let json_header = if let Some(json_header) = header.json_header {
+        format!(r#"<div class="well">{}</div>"#, util::escape_html(json_header))
+    } else {
+        "".to_string()
+    };
+
+    Result::Ok(format!(r##"
+<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>BadLog - {title}</title>
+
+    <!-- bootstrap.min.css -->
+    <style type="text/css">
+        {bootstrap_css}
+    </style>
+
+    <!-- jquery-3.2.1.min.js -->
+    <script>
+        {jquery_js}
+    </script>
+
+    <!-- bootstrap.min.js -->
+    <script>
+        {bootstrap_js}
+    </script>
+
+    <!-- highcharts.js -->
+    <script>
+        {highcharts_js}
+    </script>
+
+    <!-- boost.js -->
+    <script>
+        {boost_js}
+    </script>
+
+    <!-- exporting.js -->
+    <script>
+        {exporting_js}
+    </script>
+
+    <!-- offline-exporting.js -->
+    <script>
+        {offline_exporting_js}
+    </script>
+
+    <!-- For syncronizing chart zooms -->
+    <script>
+        function syncExtremes(e) {{
+            var thisChart = this.chart;
+
+            if (e.trigger !== 'syncExtremes') {{ // Prevent feedback loop
+                Highcharts.each(Highcharts.charts, function (chart) {{
+                    if (chart !== thisChart) {{
+                        if (chart.xAxis[0].setExtremes) {{ // It is null while updating
+                            chart.xAxis[0].setExtremes(e.min, e.max, undefined, false, {{ trigger: 'syncExtremes' }});
+                        }}
+                    }}
+                }});
+            }}
+        }}
+    </script>
+
+    <!-- For reading the embedded data, charts are drawn once it is loaded -->
+    <script>
+        var badlogCharts = [];
+        var badlogF64, badlogF32;
+
+        function badlogArray(ref) {{
+            return (ref[0] ? badlogF32 : badlogF64).subarray(ref[1], ref[1] + ref[2]);
+        }}
+
+        function badlogPoints(ref) {{
+            var xs = badlogArray(ref[0]), ys = badlogArray(ref[1]);
+            var out = new Array(xs.length);
+            for (var i = 0; i < xs.length; i++) {{
+                out[i] = [xs[i], isNaN(ys[i]) ? null : ys[i]];
+            }}
+            return out;
+        }}
+
+        function badlogLoad(base64, sections) {{
+            var binary = atob(base64);
+            var bytes = new Uint8Array(binary.length);
+            for (var i = 0; i < binary.length; i++) {{
+                bytes[i] = binary.charCodeAt(i);
+            }}
+
+            var buffer;
+            if (sections.gzip) {{
+                if (typeof DecompressionStream === 'undefined') {{
+                    document.getElementById('badlog_error').style.display = 'block';
+                    return;
+                }}
+                buffer = new Response(new Blob([bytes]).stream().pipeThrough(new DecompressionStream('gzip'))).arrayBuffer();
+            }} else {{
+                buffer = Promise.resolve(bytes.buffer);
+            }}
+
+            buffer.then(function (buffer) {{
+                badlogF64 = new Float64Array(buffer, 0, sections.f64);
+                badlogF32 = new Float32Array(buffer, 8 * sections.f64, sections.f32);
+                var csv = new Uint8Array(buffer, 8 * sections.f64 + 4 * sections.f32, sections.csv);
+                document.getElementById('badlog_csv').href = URL.createObjectURL(new Blob([csv], {{ type: 'text/csv' }}));
+                if (sections.augmented) {{
+                    var augmented = new Uint8Array(buffer, 8 * sections.f64 + 4 * sections.f32 + sections.csv, sections.augmented);
+                    document.getElementById('badlog_augmented_csv').href = URL.createObjectURL(new Blob([augmented], {{ type: 'text/csv' }}));
+                }}
+
+                Highcharts.each(badlogCharts, function (chart) {{
+                    chart();
+                }});
+            }});
+        }}
+    </script>
+
+    <!-- For drawing every point of a decimated line once zoomed in -->
+    <script>
+        var badlogFull = {{}};
+
+        // Same as util::decimate, on the points between min and max
+        function lodDecimate(xs, ys, min, max, target) {{
+            var count = xs.length;
+            var first = -1, last = -1;
+            for (var i = 0; i < count; i++) {{
+                if (xs[i] >= min && xs[i] <= max) {{
+                    if (first < 0) {{
+                        first = i;
+                    }}
+                    last = i;
+                }}
+            }}
+            if (first < 0) {{
+                return [];
+            }}
+            // One point on each side keeps the line going to the edges
+            first = Math.max(first - 1, 0);
+            last = Math.min(last + 1, count - 1);
+
+            var point = function (i) {{ return [xs[i], isNaN(ys[i]) ? null : ys[i]]; }};
+            var out = [];
+            if (last - first + 1 <= target) {{
+                for (i = first; i <= last; i++) {{
+                    out.push(point(i));
+                }}
+                return out;
+            }}
+
+            var buckets = Math.max(Math.floor(target / 2), 1);
+            var size = (last - first + 1) / buckets;
+            var keep = [first];
+            for (var bucket = 0; bucket < buckets; bucket++) {{
+                var from = first + Math.floor(bucket * size);
+                var to = Math.min(first + Math.floor((bucket + 1) * size), last + 1);
+                var low = -1, high = -1, gap = -1;
+                for (var j = from; j < to; j++) {{
+                    var y = ys[j];
+                    if (isNaN(y)) {{
+                        if (gap < 0) {{
+                            gap = j;
+                        }}
+                        continue;
+                    }}
+                    if (low < 0 || y < ys[low]) {{
+                        low = j;
+                    }}
+                    if (high < 0 || y > ys[high]) {{
+                        high = j;
+                    }}
+                }}
+                keep = keep.concat([low, high, gap].filter(function (k) {{ return k >= 0; }}).sort(function (a, b) {{ return a - b; }}));
+            }}
+            keep.push(last);
+            for (i = 0; i < keep.length; i++) {{
+                if (i === 0 || keep[i] !== keep[i - 1]) {{
+                    out.push(point(keep[i]));
+                }}
+            }}
+            return out;
+        }}
+
+        function lodExtremes(e) {{
+            var chart = this.chart;
+            var full = badlogFull[chart.renderTo.id];
+            if (!full || chart.lodUpdating) {{
+                return;
+            }}
+            var zoomed = e.userMin !== undefined || e.userMax !== undefined;
+            var min = zoomed ? e.min : -Infinity;
+            var max = zoomed ? e.max : Infinity;
+
+            chart.lodUpdating = true;
+            Highcharts.each(chart.series, function (series, i) {{
+                if (full.series[i]) {{
+                    var xs = badlogArray(full.series[i][0]), ys = badlogArray(full.series[i][1]);
+                    series.setData(lodDecimate(xs, ys, min, max, full.target), false);
+                }}
+            }});
+            chart.redraw();
+            chart.lodUpdating = false;
+        }}
+    </script>
+
+  </head>
+
+  <body>
+    <div class="container">
+      <div class="page-header">
+        <h1>{title} <a id="badlog_csv" download="{csv_filename}" class="btn btn-default btn-md">Download CSV</a>{augmented_button}</h1>
+        {window}
+        {attatched_files}
+      </div>
+
+      <div id="badlog_error" class="alert alert-danger" style="display: none">This report is compressed and needs a browser with DecompressionStream to show its graphs.</div>
+
+      {content}
+
+      <a style="color: grey; text-decoration: underline;" data-toggle="collapse" href="#metadata" aria-expanded="false" aria-controls="metadata">Info</a>
+      <div class="collapse" id="metadata">
+        {json_header}
+        <p>badlogvis {badlogvis_version}</p>
+      </div>
+    </div> <!-- /container -->
+
+    <script>
+        badlogLoad("{data_base64}", {data_sections});
+    </script>
+  </body>
+</html>"##, title = util::escape_html(input), bootstrap_css = bootstrap_css_source, jquery_js = jquery_js_source, bootstrap_js = bootstrap_js_source,
+            highcharts_js = highcharts_js_source, boost_js = highcharts_boost_js_source,
+            content = content, data_base64 = data_base64, data_sections = util::script_json(&data_sections),
+            csv_filename = csv_filename, augmented_button = augmented_button, window = window,
+            exporting_js = highcharts_exporting_js_source,
+            offline_exporting_js = highcharts_offline_exporting_source,
+            badlogvis_version = VERSION, json_header = json_header, attatched_files = attatched_file_text))
+}
@@ -1,49 +1,65 @@
-extern crate base64;
+extern crate badlogvis;
 extern crate colored;
-extern crate csv;
-extern crate flate2;
-extern crate serde;
-#[macro_use]
-extern crate serde_derive;
-extern crate serde_json;
+extern crate open;
 extern crate structopt;
 #[macro_use]
 extern crate structopt_derive;
-extern crate sha1;
-extern crate tempfile;
-extern crate open;
 
-#[macro_use]
-mod util;
-mod attached_file;
-mod attribute;
-mod folder;
-mod graph;
-mod input;
-mod xaxis;
+macro_rules! warning {
+    ($fmt:expr) => {
+        {
+            use colored::*;
+            eprintln!(concat!("{}: ", $fmt), if cfg!(windows) { "warning".normal() } else { "warning".bold().yellow() });
+        }
+    };
+    ($fmt:expr, $($arg:tt)*) => {
+        use colored::*;
+        eprintln!(concat!("{}: ", $fmt), if cfg!(windows) { "warning".normal() } else { "warning".bold().yellow() }, $($arg)*);
+    };
+}
 
+use std::convert::TryFrom;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
+use std::mem;
+use std::path::Path;
 
 use structopt::StructOpt;
 
-use attached_file::AttachedFile;
-use folder::Folder;
-use graph::Graph;
-use input::*;
-
-pub const UNITLESS: &str = "ul";
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+use badlogvis::compare::{self, Align, Run};
+use badlogvis::filter::Filter;
+use badlogvis::input::{Value, STDIO_PATH};
+use badlogvis::window::{Bound, Window};
+use badlogvis::{
+    AttachedFile, Config, Diagnostics, Error, Folder, Graph, HtmlHeader, InputFormat, OutputFormat,
+    ParseOptions, RenderOptions,
+};
 
 #[derive(StructOpt, Debug)]
-#[structopt(name = "badlogvis", about = "Create html from badlog data")]
+#[structopt(
+    name = "badlogvis",
+    about = "Create html from badlog data, or run `badlogvis convert` to turn a CSV into a bag"
+)]
 pub struct Opt {
-    #[structopt(help = "Input file")]
+    #[structopt(help = "Input file, may be gzip compressed, - for stdin")]
     input: String,
 
-    #[structopt(help = "Output file, default to <input>.html")]
+    #[structopt(help = "Output file, default to <input>.html, .md or .json, or a <input>_svg folder, - for stdout")]
     output: Option<String>,
 
+    #[structopt(
+        long = "format",
+        default_value = "html",
+        help = "Report format: html, svg for a folder of SVG files, markdown or json"
+    )]
+    format: String,
+
+    #[structopt(
+        long = "title",
+        help = "Report title, default to the input file name"
+    )]
+    title: Option<String>,
+
     #[structopt(
         short = "t",
         long = "trim-doubles",
@@ -51,11 +67,56 @@ pub struct Opt {
     )]
     trim_doubles: bool,
 
-    #[structopt(short = "c", long = "csv", help = "Input is CSV file")]
+    #[structopt(
+        short = "c",
+        long = "csv",
+        help = "Input is CSV file, detected by default"
+    )]
     csv: bool,
 
-    #[structopt(short = "g", long = "gzip", help = "Compress embeded CSV file")]
-    compress_csv: bool,
+    #[structopt(
+        long = "bag",
+        conflicts_with = "csv",
+        help = "Input is bag file, detected by default"
+    )]
+    bag: bool,
+
+    #[structopt(short = "v", long = "verbose", help = "Print progress messages")]
+    verbose: bool,
+
+    #[structopt(
+        long = "config",
+        help = "JSON file of units and attributes to add to topics"
+    )]
+    config: Option<String>,
+
+    #[structopt(long = "xaxis", help = "Use this topic as the x-axis")]
+    xaxis: Vec<String>,
+
+    #[structopt(
+        long = "attr",
+        help = "Add attributes to topics, as <topic>=<attr>,<attr>"
+    )]
+    attrs: Vec<String>,
+
+    #[structopt(long = "unit", help = "Set the unit of topics, as <topic>=<unit>")]
+    units: Vec<String>,
+
+    #[structopt(
+        short = "g",
+        long = "gzip",
+        help = "Compress the embedded graph data and CSV file"
+    )]
+    compress: bool,
+
+    #[structopt(long = "float32", help = "Embed graph values as 32-bit floats")]
+    float32: bool,
+
+    #[structopt(
+        long = "export-csv",
+        help = "Also write the CSV with derived and joined topics added, and offer it in the report"
+    )]
+    export_csv: Option<String>,
 
     #[structopt(
         short = "a",
@@ -70,206 +131,479 @@ pub struct Opt {
         help = "Open resulting HTML in default browser"
     )]
     open_in_browser: bool,
+
+    #[structopt(
+        long = "compare",
+        help = "Overlay these runs of the same code on the input's graphs"
+    )]
+    compare_paths: Vec<String>,
+
+    #[structopt(
+        long = "align",
+        help = "Line up compared runs: none, start or event:<text>"
+    )]
+    align: Option<String>,
+
+    #[structopt(
+        long = "from",
+        help = "Crop the log to start here, an x-axis position or row:<index>"
+    )]
+    from: Option<String>,
+
+    #[structopt(
+        long = "to",
+        help = "Crop the log to end here, an x-axis position or row:<index>"
+    )]
+    to: Option<String>,
+
+    #[structopt(
+        long = "while",
+        conflicts_with_all_raw = "&[\"from\", \"to\"]",
+        help = "Crop the log to where this boolean topic is true"
+    )]
+    while_topic: Option<String>,
+
+    #[structopt(
+        long = "include",
+        help = "Only keep topics, logs and values matching this glob or regex:<expr>"
+    )]
+    include: Vec<String>,
+
+    #[structopt(
+        long = "exclude",
+        help = "Drop topics, logs and values matching this glob or regex:<expr>"
+    )]
+    exclude: Vec<String>,
+
+    #[structopt(
+        long = "stack-bools",
+        help = "Stack the boolean topics of each folder into one graph"
+    )]
+    stack_bools: bool,
+
+    #[structopt(
+        long = "max-points",
+        default_value = "5000",
+        help = "Most points drawn per line before zooming in, 0 draws every point"
+    )]
+    max_points: usize,
+
+    #[structopt(
+        short = "k",
+        long = "keep-going",
+        help = "Report every error instead of stopping at the first"
+    )]
+    keep_going: bool,
 }
 
-enum CsvEmbed {
-    Raw(String),
-    Compressed(Vec<u8>),
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "badlogvis convert",
+    about = "Convert a CSV file into a bag carrying its units, attributes and values"
+)]
+pub struct ConvertOpt {
+    #[structopt(help = "Input CSV file, may be gzip compressed, - for stdin")]
+    input: String,
+
+    #[structopt(help = "Output bag file, default to <input>.bag, - for stdout")]
+    output: Option<String>,
+
+    #[structopt(
+        short = "t",
+        long = "trim-doubles",
+        help = "Retry parsing doubles without whitespace"
+    )]
+    trim_doubles: bool,
+
+    #[structopt(short = "v", long = "verbose", help = "Print progress messages")]
+    verbose: bool,
+
+    #[structopt(
+        long = "config",
+        help = "JSON file of units and attributes to add to topics"
+    )]
+    config: Option<String>,
+
+    #[structopt(long = "xaxis", help = "Use this topic as the x-axis")]
+    xaxis: Vec<String>,
+
+    #[structopt(
+        long = "attr",
+        help = "Add attributes to topics, as <topic>=<attr>,<attr>"
+    )]
+    attrs: Vec<String>,
+
+    #[structopt(long = "unit", help = "Set the unit of topics, as <topic>=<unit>")]
+    units: Vec<String>,
+
+    #[structopt(long = "value", help = "Add a value to the bag, as <name>=<value>")]
+    values: Vec<String>,
+
+    #[structopt(
+        short = "k",
+        long = "keep-going",
+        help = "Report every error instead of stopping at the first"
+    )]
+    keep_going: bool,
 }
 
 fn main() {
-    let opt: Opt = Opt::from_args();
+    // The report takes its input as the first argument, so the subcommand is picked out by hand
+    if std::env::args().nth(1).is_some_and(|arg| arg == "convert") {
+        let opt = ConvertOpt::from_clap(
+            ConvertOpt::clap().get_matches_from(std::env::args().skip(1)),
+        );
+        let mut diag = new_diagnostics(opt.keep_going);
+        let result = convert(&opt, &mut diag);
+        finish(result, &diag, opt.verbose);
+        return;
+    }
 
-    let input_path = opt.input.clone();
-    let output = opt
-        .output
-        .clone()
-        .unwrap_or_else(|| format!("{}.html", input_path));
+    let opt: Opt = Opt::from_args();
+    let mut diag = new_diagnostics(opt.keep_going);
+    let result = run(&opt, &mut diag);
+    finish(result, &diag, opt.verbose);
+}
 
-    let mut input = parse_input(&input_path, &opt);
+fn new_diagnostics(keep_going: bool) -> Diagnostics {
+    if keep_going {
+        Diagnostics::collecting()
+    } else {
+        Diagnostics::new()
+    }
+}
 
-    let (graphs, xaxis) = Graph::gen_graphs(&input.topics);
+/// Prints the messages collected while running, exiting with an error code if it failed
+fn finish(result: badlogvis::Result<()>, diag: &Diagnostics, verbose: bool) {
+    if verbose {
+        for message in &diag.notes {
+            eprintln!("{}", message);
+        }
+    }
 
-    for log in &mut input.logs {
-        log.apply_xaxis(&xaxis);
+    for message in &diag.warnings {
+        warning!("{}", message);
     }
 
-    let folders: Vec<Folder> = Folder::gen_folders(graphs, input.values, input.logs);
+    if let Err(e) = result {
+        match e {
+            Error::Multiple(errors) => {
+                for e in &errors {
+                    print_error(e);
+                }
+            }
+            e => print_error(&e),
+        }
+        std::process::exit(1);
+    }
+}
 
-    let csv_embed = if opt.compress_csv {
-        use flate2::write::GzEncoder;
-        use flate2::Compression;
-        use std::io::prelude::*;
+fn print_error(e: &Error) {
+    use colored::*;
+    eprintln!(
+        "{}: {}",
+        if cfg!(windows) {
+            "error".normal()
+        } else {
+            "error".bold().red()
+        },
+        e
+    );
+}
 
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(&input.csv_text.as_bytes()).unwrap();
-        CsvEmbed::Compressed(encoder.finish().unwrap())
-    } else {
-        CsvEmbed::Raw(input.csv_text)
+/// Config file merged with the topic options from the command line
+fn topic_config(
+    config_path: &Option<String>,
+    xaxis: &[String],
+    attrs: &[String],
+    units: &[String],
+) -> badlogvis::Result<Option<Config>> {
+    let mut config = match *config_path {
+        Some(ref config_path) => Option::Some(Config::from_path(config_path)?),
+        None => Option::None,
     };
 
-    let attatched_files = {
-        let mut out = Vec::<AttachedFile>::new();
-        for path in &opt.attatched_paths {
-            let file = AttachedFile::from(path.as_ref());
-            if out.iter().filter(|f| &f.path == path).count() > 0 {
-                warning!("Duplicate paths found for {}", path);
-            } else if out.iter().filter(|f| &f.name == &file.name).count() > 0 {
-                warning!("Attatched two files with same base name: {}", file.name);
-            }
-            out.push(file);
+    // Command line topic options go after the config file so they win
+    if !xaxis.is_empty() || !attrs.is_empty() || !units.is_empty() {
+        let config = config.get_or_insert_with(Config::default);
+        for name in xaxis {
+            config.push_xaxis(name);
         }
-        out
-    };
+        for attrs in attrs {
+            config.push_attrs(attrs)?;
+        }
+        for unit in units {
+            config.push_unit(unit)?;
+        }
+    }
 
-    let out = gen_html(
-        &input_path,
-        folders,
-        &csv_embed,
-        input.json_header_text.as_ref().map(String::as_str),
-        attatched_files,
-    );
+    Result::Ok(config)
+}
 
-    let output_path = output.clone();
+/// Writes `out` to the file at `output`, or to stdout for `-`
+fn write_output(output: &str, out: &str) -> badlogvis::Result<()> {
+    if output == STDIO_PATH {
+        let stdout = io::stdout();
+        if let Err(e) = stdout.lock().write_all(out.as_bytes()) {
+            return Result::Err(Error::Write("stdout".to_string(), e));
+        }
+        return Result::Ok(());
+    }
 
-    let mut outfile = File::create(output).unwrap();
-    outfile.write_all(out.as_bytes()).unwrap();
+    let mut outfile = match File::create(output) {
+        Ok(file) => file,
+        Err(e) => return Result::Err(Error::Write(output.to_string(), e)),
+    };
+    match outfile.write_all(out.as_bytes()) {
+        Ok(_) => Result::Ok(()),
+        Err(e) => Result::Err(Error::Write(output.to_string(), e)),
+    }
+}
 
-    if opt.open_in_browser {
-        match open::that(output_path) {
-            Ok(_) => (),
-            Err(_) => warning!("There was an error opening the browser."),
+fn convert(opt: &ConvertOpt, diag: &mut Diagnostics) -> badlogvis::Result<()> {
+    let output = match opt.output {
+        Some(ref output) => output.clone(),
+        None if opt.input == STDIO_PATH => STDIO_PATH.to_string(),
+        None => {
+            let name = badlogvis::input::display_name(&opt.input);
+            let stem = if name.to_ascii_lowercase().ends_with(".csv") {
+                &name[..name.len() - 4]
+            } else {
+                name
+            };
+            format!("{}.bag", stem)
         }
+    };
+
+    let options = ParseOptions {
+        trim_doubles: opt.trim_doubles,
+        format: InputFormat::Csv,
+        config: topic_config(&opt.config, &opt.xaxis, &opt.attrs, &opt.units)?,
+    };
+
+    let mut input = badlogvis::parse_input(&opt.input, &options, diag)?;
+    for value in &opt.values {
+        input.values.push(Value::from_option(value)?);
     }
+
+    write_output(&output, &input.to_bag()?)
 }
 
-fn gen_html(
-    input: &str,
-    folders: Vec<Folder>,
-    csv_embed: &CsvEmbed,
-    json_header: Option<&str>,
-    attatched_files: Vec<AttachedFile>,
-) -> String {
-    let bootstrap_css_source = include_str!("web_res/bootstrap.min.css");
-    let jquery_js_source = include_str!("web_res/jquery-3.2.1.min.js");
-    let bootstrap_js_source = include_str!("web_res/bootstrap.min.js");
-    let highcharts_js_source = include_str!("web_res/highcharts.js");
-    let highcharts_boost_js_source = include_str!("web_res/boost.js");
-    let highcharts_exporting_js_source = include_str!("web_res/exporting.js");
-    let highcharts_offline_exporting_source = include_str!("web_res/offline-exporting.js");
-
-    let (csv_base64, extention) = match csv_embed {
-        CsvEmbed::Raw(ref csv_raw) => (base64::encode(csv_raw), "csv"),
-        CsvEmbed::Compressed(ref data) => (base64::encode(data), "csv.gz"),
+fn run(opt: &Opt, diag: &mut Diagnostics) -> badlogvis::Result<()> {
+    let input_path = opt.input.clone();
+    let title = match opt.title {
+        Some(ref title) => title.clone(),
+        None if input_path == STDIO_PATH => "stdin".to_string(),
+        None => badlogvis::input::display_name(&input_path).to_string(),
+    };
+    let format = OutputFormat::from(&opt.format)?;
+    // Reading from a pipe most likely means writing to one too
+    let output = match opt.output {
+        Some(ref output) => output.clone(),
+        None if input_path == STDIO_PATH && format.is_single_file() => STDIO_PATH.to_string(),
+        None if input_path == STDIO_PATH => format.default_output("stdin"),
+        None => format.default_output(badlogvis::input::display_name(&input_path)),
+    };
+    if output == STDIO_PATH && !format.is_single_file() {
+        return Result::Err(Error::StdoutFormat(opt.format.clone()));
+    }
+
+    let config = topic_config(&opt.config, &opt.xaxis, &opt.attrs, &opt.units)?;
+
+    let options = ParseOptions {
+        trim_doubles: opt.trim_doubles,
+        format: if opt.csv {
+            InputFormat::Csv
+        } else if opt.bag {
+            InputFormat::Bag
+        } else {
+            InputFormat::Auto
+        },
+        config,
+    };
+
+    let window = if let Some(ref name) = opt.while_topic {
+        Option::Some(Window::While(name.clone()))
+    } else if opt.from.is_some() || opt.to.is_some() {
+        let bound = |text: &Option<String>| text.as_ref().map(|t| Bound::from(t)).transpose();
+        Option::Some(Window::Range {
+            from: bound(&opt.from)?,
+            to: bound(&opt.to)?,
+        })
+    } else {
+        Option::None
     };
-    let csv_filename = format!("{}.{}", input, extention);
 
-    let mut content = String::new();
+    let filter = Filter::new(&opt.include, &opt.exclude)?;
 
-    for folder in folders {
-        content += &folder.gen_html();
+    let mut input = badlogvis::parse_input(&input_path, &options, diag)?;
+    let window_text = match window {
+        Some(ref window) => Option::Some(window.crop(&mut input)?),
+        None => Option::None,
+    };
+    if !filter.is_empty() {
+        filter.apply(&title, &mut input, diag);
     }
 
-    let attatched_file_text = if attatched_files.is_empty() {
-        "".to_string()
+    // Only the main input's CSV and header are embedded when comparing runs
+    let csv_text = mem::take(&mut input.csv_text);
+    let json_header_text = input.json_header_text.take();
+
+    let mut augmented_csv: Option<String> = Option::None;
+
+    let folders: Vec<Folder> = if opt.compare_paths.is_empty() {
+        let (mut graphs, xaxis) = Graph::gen_graphs(&input.topics, diag)?;
+
+        if opt.export_csv.is_some() {
+            augmented_csv = Option::Some(badlogvis::export::gen_augmented_csv(
+                &csv_text, &graphs, &xaxis, diag,
+            )?);
+        }
+
+        if opt.stack_bools {
+            graphs = Graph::stack_bools(graphs);
+        }
+
+        for log in &mut input.logs {
+            log.apply_xaxis(&xaxis);
+        }
+
+        Folder::gen_folders(graphs, input.values, input.logs)
     } else {
-        let mut files = "".to_string();
-        for file in &attatched_files {
-            files += &file.get_button_html();
+        let align = match opt.align {
+            Some(ref align) => Align::from(align)?,
+            None => Align::None,
+        };
+
+        let mut runs = vec![Run::new(&input_path, input)];
+        for path in &opt.compare_paths {
+            let mut run = badlogvis::parse_input(path, &options, diag)?;
+            if let Some(ref window) = window {
+                window.crop(&mut run)?;
+            }
+            if !filter.is_empty() {
+                filter.apply(path, &mut run, diag);
+            }
+            runs.push(Run::new(path, run));
+        }
+
+        let (mut graphs, values, logs) = compare::gen_compare(runs, &align, diag)?;
+
+        if opt.export_csv.is_some() {
+            diag.warn("Runs being compared don't share rows, so no CSV is exported.");
+        }
+
+        if opt.stack_bools {
+            graphs = Graph::stack_bools(graphs);
         }
 
-        format!("<br />\n{}", files)
+        Folder::gen_folders(graphs, values, logs)
     };
 
-    let json_header = if let Some(header) = json_header {
-        format!(r#"<div class="well">{}</div>"#, header)
+    if let (Some(path), Some(augmented_csv)) = (opt.export_csv.as_ref(), augmented_csv.as_ref()) {
+        badlogvis::write_file(Path::new(path), augmented_csv)?;
+    }
+
+    let render = RenderOptions {
+        max_points: if opt.max_points == 0 {
+            Option::None
+        } else {
+            Option::Some(opt.max_points)
+        },
+        compress: opt.compress,
+        float32: opt.float32,
+    };
+
+    if format == OutputFormat::Svg {
+        if !opt.attatched_paths.is_empty() {
+            diag.warn("Attached files are only included in HTML reports.");
+        }
+
+        badlogvis::svg::write_svg_tree(&output, &title, &folders, &render)?;
+
+        if opt.open_in_browser {
+            let index = Path::new(&output).join("index.html");
+            if open::that(index).is_err() {
+                diag.warn("There was an error opening the browser.");
+            }
+        }
+
+        return Result::Ok(());
+    }
+
+    if format == OutputFormat::Markdown {
+        if !opt.attatched_paths.is_empty() {
+            diag.warn("Attached files are only included in HTML reports.");
+        }
+
+        let out = badlogvis::markdown::gen_markdown(
+            Path::new(&output),
+            &title,
+            &folders,
+            json_header_text.as_deref(),
+            &render,
+        )?;
+        badlogvis::write_file(Path::new(&output), &out)?;
+
+        if opt.open_in_browser {
+            diag.warn("Can't open a Markdown report in the browser.");
+        }
+
+        return Result::Ok(());
+    }
+
+    let out = if format == OutputFormat::Json {
+        if !opt.attatched_paths.is_empty() {
+            diag.warn("Attached files are only included in HTML reports.");
+        }
+
+        badlogvis::export::gen_json(
+            &title,
+            &folders,
+            json_header_text.as_deref(),
+        )
     } else {
-        "".to_string()
+        let attatched_files = {
+            let mut out = Vec::<AttachedFile>::new();
+            for path in &opt.attatched_paths {
+                let file = AttachedFile::try_from(path.as_ref())?;
+                if out.iter().filter(|f| &f.path == path).count() > 0 {
+                    diag.warn(format!("Duplicate paths found for {}", path));
+                } else if out.iter().filter(|f| &f.name == &file.name).count() > 0 {
+                    diag.warn(format!(
+                        "Attatched two files with same base name: {}",
+                        file.name
+                    ));
+                }
+                out.push(file);
+            }
+            out
+        };
+
+        badlogvis::gen_html(
+            &title,
+            folders,
+            &csv_text,
+            HtmlHeader {
+                augmented_csv: augmented_csv.as_deref(),
+                json_header: json_header_text.as_deref(),
+                window: window_text.as_deref(),
+                attatched_files,
+            },
+            &render,
+        )?
     };
 
-    format!(r##"
-<!DOCTYPE html>
-<html lang="en">
-  <head>
-    <meta charset="utf-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1">
-    <title>BadLog - {title}</title>
-
-    <!-- bootstrap.min.css -->
-    <style type="text/css">
-        {bootstrap_css}
-    </style>
-
-    <!-- jquery-3.2.1.min.js -->
-    <script>
-        {jquery_js}
-    </script>
-
-    <!-- bootstrap.min.js -->
-    <script>
-        {bootstrap_js}
-    </script>
-
-    <!-- highcharts.js -->
-    <script>
-        {highcharts_js}
-    </script>
-
-    <!-- boost.js -->
-    <script>
-        {boost_js}
-    </script>
-
-    <!-- exporting.js -->
-    <script>
-        {exporting_js}
-    </script>
-
-    <!-- offline-exporting.js -->
-    <script>
-        {offline_exporting_js}
-    </script>
-
-    <!-- For syncronizing chart zooms -->
-    <script>
-        function syncExtremes(e) {{
-            var thisChart = this.chart;
-
-            if (e.trigger !== 'syncExtremes') {{ // Prevent feedback loop
-                Highcharts.each(Highcharts.charts, function (chart) {{
-                    if (chart !== thisChart) {{
-                        if (chart.xAxis[0].setExtremes) {{ // It is null while updating
-                            chart.xAxis[0].setExtremes(e.min, e.max, undefined, false, {{ trigger: 'syncExtremes' }});
-                        }}
-                    }}
-                }});
-            }}
-        }}
-    </script>
-
-  </head>
-
-  <body>
-    <div class="container">
-      <div class="page-header">
-        <h1>{title} <a href="data:text/csv;base64,{csv_base64}" download="{csv_filename}" class="btn btn-default btn-md">Download {extention}</a></h1>
-        {attatched_files}
-      </div>
-
-      {content}
-
-      <a style="color: grey; text-decoration: underline;" data-toggle="collapse" href="#metadata" aria-expanded="false" aria-controls="metadata">Info</a>
-      <div class="collapse" id="metadata">
-        {json_header}
-        <p>badlogvis {badlogvis_version}</p>
-      </div>
-    </div> <!-- /container -->
-  </body>
-</html>"##, title = input, bootstrap_css = bootstrap_css_source, jquery_js = jquery_js_source, bootstrap_js = bootstrap_js_source,
-            highcharts_js = highcharts_js_source, boost_js = highcharts_boost_js_source,
-            content = content, csv_base64 = csv_base64, csv_filename = csv_filename,
-            exporting_js = highcharts_exporting_js_source,
-            offline_exporting_js = highcharts_offline_exporting_source,
-            extention = extention.to_string().to_uppercase(),
-            badlogvis_version = VERSION, json_header = json_header, attatched_files = attatched_file_text)
+    write_output(&output, &out)?;
+
+    if opt.open_in_browser && output == STDIO_PATH {
+        diag.warn("Can't open the browser when writing to stdout.");
+    } else if opt.open_in_browser {
+        match open::that(output) {
+            Ok(_) => (),
+            Err(_) => diag.warn("There was an error opening the browser."),
+        }
+    }
+
+    Result::Ok(())
 }
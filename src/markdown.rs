@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use error::Result;
+use folder::Folder;
+use graph::{Graph, RenderOptions};
+use svg;
+use util::{self, escape_markdown, markdown_code_block, markdown_table};
+
+/// Number rounded to six significant digits, without trailing zeros
+fn round_number(value: f64) -> String {
+    if value == 0f64 || !value.is_finite() {
+        return value.to_string();
+    }
+    let decimals = (5f64 - value.abs().log10().floor()).max(0f64) as usize;
+    let text = format!("{:.*}", decimals, value);
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+impl Graph {
+    /// Statistics of each series as a Markdown table, matching the summaries under the HTML charts
+    pub fn gen_markdown_summary(&self) -> String {
+        if let Some(ref states) = self.states {
+            let several = self.series.len() > 1;
+            let mut rows: Vec<Vec<String>> = Vec::new();
+            for s in &self.series {
+                let segments = util::state_segments(&s.data);
+                for (state, name) in states.iter().enumerate() {
+                    let entries = segments.iter().filter(|seg| seg.0 == state).count();
+                    if entries == 0 {
+                        continue;
+                    }
+                    let total: f64 = segments
+                        .iter()
+                        .filter(|seg| seg.0 == state)
+                        .map(|seg| seg.2 - seg.1)
+                        .sum();
+                    let mut row = if several {
+                        vec![s.name.clone()]
+                    } else {
+                        Vec::new()
+                    };
+                    row.extend(vec![name.clone(), entries.to_string(), round_number(total)]);
+                    rows.push(row);
+                }
+            }
+            let mut head = if several { vec!["Series"] } else { Vec::new() };
+            head.extend(vec!["State", "Entries", "Total"]);
+            return markdown_table(&head, &rows);
+        }
+
+        if self.digital {
+            let rows: Vec<Vec<String>> = self
+                .series
+                .iter()
+                .map(|s| {
+                    let (rising, falling) = util::digital_edges(&s.data);
+                    let high: f64 = util::state_segments(&s.data)
+                        .iter()
+                        .filter(|seg| seg.0 == 1)
+                        .map(|seg| seg.2 - seg.1)
+                        .sum();
+                    vec![
+                        s.name.clone(),
+                        rising.to_string(),
+                        falling.to_string(),
+                        round_number(high),
+                    ]
+                })
+                .collect();
+            return markdown_table(&["Series", "Rising", "Falling", "Total true"], &rows);
+        }
+
+        let rows: Vec<Vec<String>> = self
+            .series
+            .iter()
+            .map(|s| {
+                let values: Vec<f64> = s.data.iter().map(|p| p.1).filter(|y| !y.is_nan()).collect();
+                if values.is_empty() {
+                    return vec![s.name.clone(), "0".to_string()];
+                }
+                let count = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / count;
+                let deviation =
+                    (values.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / count).sqrt();
+                vec![
+                    s.name.clone(),
+                    values.len().to_string(),
+                    round_number(values.iter().cloned().fold(f64::INFINITY, f64::min)),
+                    round_number(values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
+                    round_number(mean),
+                    round_number(deviation),
+                ]
+            })
+            .collect();
+        markdown_table(
+            &["Series", "Samples", "Min", "Max", "Mean", "Std dev"],
+            &rows,
+        )
+    }
+}
+
+/// Assets folder next to a Markdown report, `report.md` keeps its images in `report_assets`
+pub fn assets_dir(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map_or("report".to_string(), |stem| {
+        stem.to_string_lossy().into_owned()
+    });
+    path.with_file_name(format!("{}_assets", stem))
+}
+
+/// Markdown report with the values, logs and statistics of every folder, graphs are written as
+/// SVG files into the assets folder the report links to
+pub fn gen_markdown(
+    path: &Path,
+    input: &str,
+    folders: &[Folder],
+    json_header: Option<&str>,
+    render: &RenderOptions,
+) -> Result<String> {
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let assets = assets_dir(path);
+    let mut written: Vec<PathBuf> = Vec::new();
+
+    let mut content = format!("# {}\n\n", escape_markdown(input));
+    if let Some(header) = json_header {
+        content += &markdown_code_block(header);
+        content += "\n";
+    }
+
+    for folder in folders {
+        let images: Vec<String> = svg::write_folder_svgs(&assets, folder, &mut written, render)?
+            .iter()
+            .map(|image| svg::relative_link(image, base))
+            .collect();
+        content += &folder.gen_markdown(&images);
+    }
+
+    content += &format!("_badlogvis {}_\n", ::VERSION);
+    Result::Ok(content)
+}
@@ -0,0 +1,458 @@
+use std::cmp::Ordering::Equal;
+use std::path::{Path, PathBuf};
+
+use error::{Error, Result};
+use folder::Folder;
+use graph::{Graph, RenderOptions, DIGITAL_SPACING};
+use util::{self, escape_html};
+
+const WIDTH: f64 = 800f64;
+const MARGIN_TOP: f64 = 60f64;
+const MARGIN_RIGHT: f64 = 20f64;
+const MARGIN_BOTTOM: f64 = 50f64;
+const LEGEND_HEIGHT: f64 = 25f64;
+
+/// Highcharts' default series colors, so both outputs look alike
+const COLORS: [&str; 10] = [
+    "#7cb5ec", "#434348", "#90ed7d", "#f7a35c", "#8085e9", "#f15c80", "#e4d354", "#2b908f",
+    "#f45b5b", "#91e8e1",
+];
+
+/// Maps data coordinates onto the plot area
+struct Frame {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+}
+
+impl Frame {
+    fn x(&self, x: f64) -> f64 {
+        self.left + (x - self.x_min) / (self.x_max - self.x_min) * self.width
+    }
+
+    fn y(&self, y: f64) -> f64 {
+        self.top + (self.y_max - y) / (self.y_max - self.y_min) * self.height
+    }
+}
+
+/// Smallest and largest value, widened when they are equal so they can be scaled between
+fn range<I: Iterator<Item = f64>>(values: I) -> (f64, f64) {
+    let values: Vec<f64> = values.filter(|v| !v.is_nan()).collect();
+    let min = values.iter().cloned().min_by(|a, b| a.partial_cmp(b).unwrap_or(Equal));
+    let max = values.iter().cloned().max_by(|a, b| a.partial_cmp(b).unwrap_or(Equal));
+    match (min, max) {
+        (Some(min), Some(max)) if min < max => (min, max),
+        (Some(value), Some(_)) => (value - 1f64, value + 1f64),
+        _ => (0f64, 1f64),
+    }
+}
+
+/// Round numbered ticks covering `min` to `max`, about `count` of them
+fn ticks(min: f64, max: f64, count: usize) -> Vec<(f64, String)> {
+    let raw_step = (max - min) / count as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let normalized = raw_step / magnitude;
+    let step = magnitude
+        * if normalized < 1.5 {
+            1f64
+        } else if normalized < 3f64 {
+            2f64
+        } else if normalized < 7f64 {
+            5f64
+        } else {
+            10f64
+        };
+    let decimals = (-step.log10().floor()).max(0f64) as usize;
+
+    let mut out = Vec::new();
+    let mut tick = (min / step).ceil() * step;
+    while tick <= max + step * 1e-9 {
+        // Avoids printing -0
+        let value = if tick.abs() < step * 1e-9 { 0f64 } else { tick };
+        out.push((value, format!("{:.*}", decimals, value)));
+        tick += step;
+    }
+    out
+}
+
+/// Path through the points, a gap starts a new subpath. Step lines hold each value until the next point.
+fn line_path(frame: &Frame, data: &[(f64, f64)], step: bool) -> String {
+    let mut path = String::new();
+    let mut last: Option<(f64, f64)> = Option::None;
+    for &(x, y) in data {
+        if y.is_nan() {
+            last = Option::None;
+            continue;
+        }
+        let (px, py) = (frame.x(x), frame.y(y));
+        match last {
+            None => path += &format!("M{:.1},{:.1}", px, py),
+            Some((_, last_py)) => {
+                if step {
+                    path += &format!("L{:.1},{:.1}", px, last_py);
+                }
+                path += &format!("L{:.1},{:.1}", px, py);
+            }
+        }
+        last = Some((px, py));
+    }
+    path
+}
+
+/// Closed shapes between each unbroken stretch of the line and `base`
+fn area_path(frame: &Frame, data: &[(f64, f64)], base: f64) -> String {
+    let mut path = String::new();
+    let base = frame.y(base.max(frame.y_min).min(frame.y_max));
+    let mut stretch: Vec<(f64, f64)> = Vec::new();
+    for &(x, y) in data.iter().chain(Some(&(f64::NAN, f64::NAN))) {
+        if y.is_nan() {
+            if !stretch.is_empty() {
+                path += &format!("M{:.1},{:.1}", stretch[0].0, base);
+                for &(px, py) in &stretch {
+                    path += &format!("L{:.1},{:.1}", px, py);
+                }
+                path += &format!("L{:.1},{:.1}Z", stretch[stretch.len() - 1].0, base);
+                stretch.clear();
+            }
+            continue;
+        }
+        stretch.push((frame.x(x), frame.y(y)));
+    }
+    path
+}
+
+impl Graph {
+    /// Standalone picture of this graph, drawn like its chart in the HTML report
+    pub fn gen_svg(&self, render: &RenderOptions) -> Result<String> {
+        if self.series.iter().any(|s| s.data.is_empty()) {
+            return Result::Err(Error::NoDataPoints);
+        }
+
+        let stacked = self.digital && self.series.len() > 1;
+        let step = self.digital || self.states.is_some();
+        let count = self.series.len();
+
+        let lines: Vec<Vec<(f64, f64)>> = self
+            .series
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if self.digital {
+                    let offset = DIGITAL_SPACING * (count - 1 - i) as f64;
+                    util::step_changes(&util::points(&s.data, offset))
+                } else if step {
+                    util::step_changes(&util::points(&s.data, 0f64))
+                } else {
+                    let data = util::points(&s.data, 0f64);
+                    match render.max_points {
+                        Some(max_points) => util::decimate(&data, max_points),
+                        None => data,
+                    }
+                }
+            })
+            .collect();
+
+        let (y_min, y_max, y_ticks): (f64, f64, Vec<(f64, String)>) =
+            if let Some(ref states) = self.states {
+                let labels = states
+                    .iter()
+                    .enumerate()
+                    .map(|(i, state)| (i as f64, state.clone()))
+                    .collect();
+                (-0.5, states.len() as f64 - 0.5, labels)
+            } else if stacked {
+                let labels = self
+                    .series
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (DIGITAL_SPACING * (count - 1 - i) as f64 + 0.5, s.name.clone()))
+                    .collect();
+                (-0.25, DIGITAL_SPACING * (count - 1) as f64 + 1.25, labels)
+            } else if self.digital {
+                let labels = vec![(0f64, "false".to_string()), (1f64, "true".to_string())];
+                (-0.25, 1.25, labels)
+            } else {
+                let (mut y_min, y_max) =
+                    range(lines.iter().flat_map(|line| line.iter().map(|p| p.1)));
+                if self.zero && y_min > 0f64 {
+                    y_min = 0f64;
+                }
+                let y_ticks = ticks(y_min, y_max, 6);
+                (y_min, y_max, y_ticks)
+            };
+
+        let (x_min, x_max) = range(lines.iter().flat_map(|line| line.iter().map(|p| p.0)));
+
+        let legend = count > 1 && !stacked;
+        let height = if let Some(ref states) = self.states {
+            150f64 + 30f64 * states.len() as f64
+        } else if self.digital {
+            150f64 + 40f64 * (count - 1) as f64
+        } else {
+            400f64
+        };
+        let label_width = y_ticks
+            .iter()
+            .map(|tick| tick.1.chars().count())
+            .max()
+            .unwrap_or(0) as f64
+            * 7f64;
+        let left = (label_width + 30f64).clamp(60f64, 200f64);
+        let frame = Frame {
+            left,
+            top: MARGIN_TOP,
+            width: WIDTH - left - MARGIN_RIGHT,
+            height: height
+                - MARGIN_TOP
+                - MARGIN_BOTTOM
+                - if legend { LEGEND_HEIGHT } else { 0f64 },
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+        };
+
+        let mut body = String::new();
+
+        // State bands go under everything else, like the HTML plot bands
+        if let (Some(_), 1) = (self.states.as_ref(), count) {
+            for (state, from, to) in util::state_segments(&self.series[0].data) {
+                body += &format!(
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" fill-opacity="0.3"/>"#,
+                    frame.x(from),
+                    frame.top,
+                    frame.x(to) - frame.x(from),
+                    frame.height,
+                    COLORS[state % COLORS.len()]
+                );
+                body += "\n";
+            }
+        }
+
+        for (y, label) in &y_ticks {
+            let py = frame.y(*y);
+            if !stacked {
+                body += &format!(
+                    r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#e6e6e6"/>"##,
+                    frame.left,
+                    py,
+                    frame.left + frame.width,
+                    py
+                );
+            }
+            body += &format!(
+                r##"<text x="{:.1}" y="{:.1}" text-anchor="end" font-size="11" fill="#666666">{}</text>"##,
+                frame.left - 8f64,
+                py + 4f64,
+                escape_html(label)
+            );
+            body += "\n";
+        }
+
+        for (x, label) in ticks(x_min, x_max, 8) {
+            let px = frame.x(x);
+            let bottom = frame.top + frame.height;
+            body += &format!(
+                r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ccd6eb"/><text x="{:.1}" y="{:.1}" text-anchor="middle" font-size="11" fill="#666666">{}</text>"##,
+                px,
+                bottom,
+                px,
+                bottom + 5f64,
+                px,
+                bottom + 18f64,
+                escape_html(&label)
+            );
+            body += "\n";
+        }
+        body += &format!(
+            r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ccd6eb"/>"##,
+            frame.left,
+            frame.top + frame.height,
+            frame.left + frame.width,
+            frame.top + frame.height
+        );
+        body += "\n";
+
+        for (i, line) in lines.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            if self.area {
+                body += &format!(
+                    r#"<path d="{}" fill="{}" fill-opacity="0.75"/>"#,
+                    area_path(&frame, line, 0f64),
+                    color
+                );
+                body += "\n";
+            }
+            body += &format!(
+                r#"<path d="{}" fill="none" stroke="{}" stroke-width="2" stroke-linejoin="round"/>"#,
+                line_path(&frame, line, step),
+                color
+            );
+            body += "\n";
+        }
+
+        if legend {
+            let y = height - LEGEND_HEIGHT + 5f64;
+            let mut x = frame.left;
+            for (i, s) in self.series.iter().enumerate() {
+                body += &format!(
+                    r##"<rect x="{:.1}" y="{:.1}" width="12" height="12" fill="{}"/><text x="{:.1}" y="{:.1}" font-size="12" fill="#333333">{}</text>"##,
+                    x,
+                    y,
+                    COLORS[i % COLORS.len()],
+                    x + 16f64,
+                    y + 11f64,
+                    escape_html(&s.name)
+                );
+                body += "\n";
+                x += 30f64 + 7f64 * s.name.chars().count() as f64;
+            }
+        }
+
+        let title = if step {
+            self.name_base.clone()
+        } else {
+            format!("{} ({})", self.name_base, self.unit)
+        };
+
+        Result::Ok(format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="Lucida Grande, Lucida Sans Unicode, Arial, Helvetica, sans-serif">
+<rect width="{width}" height="{height}" fill="#ffffff"/>
+<text x="{center}" y="24" text-anchor="middle" font-size="18" fill="#333333">{title}</text>
+<text x="{center}" y="44" text-anchor="middle" font-size="12" fill="#666666">{subtitle}</text>
+{body}<text x="{x_center}" y="{x_title}" text-anchor="middle" font-size="12" fill="#666666">{x_unit}</text>
+</svg>
+"##,
+            width = WIDTH,
+            height = height,
+            center = WIDTH / 2f64,
+            title = escape_html(&title),
+            subtitle = escape_html(&self.subtitle()),
+            body = body,
+            x_center = frame.left + frame.width / 2f64,
+            x_title = frame.top + frame.height + 38f64,
+            x_unit = escape_html(&self.x_unit)
+        ))
+    }
+}
+
+/// Name with the characters some file systems don't allow replaced
+fn safe_name(name: &str) -> String {
+    // `.` and `..` would point outside the folder the file goes in
+    if name.chars().all(|c| c == '.') {
+        return "_".repeat(name.len().max(1));
+    }
+    name.chars()
+        .map(|c| match c {
+            '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// File name for a graph that isn't taken yet
+fn file_name(name_base: &str, taken: &[PathBuf], dir: &Path) -> PathBuf {
+    let base = safe_name(name_base);
+    let mut path = dir.join(format!("{}.svg", base));
+    let mut n = 2;
+    while taken.contains(&path) {
+        path = dir.join(format!("{} {}.svg", base, n));
+        n += 1;
+    }
+    path
+}
+
+/// Link to a file from a page in `dir`, escaped for use in a URL
+pub fn relative_link(path: &Path, dir: &Path) -> String {
+    let parts: Vec<String> = path
+        .strip_prefix(dir)
+        .unwrap()
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts
+        .join("/")
+        .replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// Writes an SVG for each graph of `folder` into the subfolder of `dir` named after it,
+/// returning the files written in order. `written` holds every file written so far.
+pub fn write_folder_svgs(
+    dir: &Path,
+    folder: &Folder,
+    written: &mut Vec<PathBuf>,
+    render: &RenderOptions,
+) -> Result<Vec<PathBuf>> {
+    let folder_dir = folder
+        .name
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .fold(dir.to_path_buf(), |path, part| path.join(safe_name(part)));
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for graph in &folder.graphs {
+        let path = file_name(&graph.name_base, written, &folder_dir);
+        ::write_file(&path, &graph.gen_svg(render)?)?;
+        written.push(path.clone());
+        paths.push(path);
+    }
+    Result::Ok(paths)
+}
+
+/// Writes one SVG per graph into a folder tree under `dir` that mirrors the report's folders,
+/// with an `index.html` showing them all
+pub fn write_svg_tree(
+    dir: &str,
+    input: &str,
+    folders: &[Folder],
+    render: &RenderOptions,
+) -> Result<()> {
+    let dir = Path::new(dir);
+    let mut written: Vec<PathBuf> = Vec::new();
+    let mut content = String::new();
+
+    for folder in folders {
+        let images: Vec<String> = write_folder_svgs(dir, folder, &mut written, render)?
+            .iter()
+            .map(|path| relative_link(path, dir))
+            .collect();
+        content += &folder.gen_static_html(&images);
+    }
+
+    let index = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>BadLog - {title}</title>
+    <style type="text/css">
+        {bootstrap_css}
+    </style>
+  </head>
+  <body>
+    <div class="container">
+      <div class="page-header">
+        <h1>{title}</h1>
+      </div>
+      {content}
+      <p style="color: grey">badlogvis {badlogvis_version}</p>
+    </div>
+  </body>
+</html>
+"#,
+        title = escape_html(input),
+        bootstrap_css = include_str!("web_res/bootstrap.min.css"),
+        content = content,
+        badlogvis_version = ::VERSION
+    );
+    ::write_file(&dir.join("index.html"), &index)
+}
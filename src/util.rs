@@ -1,34 +1,6 @@
-macro_rules! error {
-    ($fmt:expr) => {
-        {
-            use std;
-            use colored::*;
-            println!(concat!("{}: ", $fmt), if cfg!(windows) { "error".normal() } else { "error".bold().red() });
-            std::process::exit(1);
-        }
-    };
-    ($fmt:expr, $($arg:tt)*) => {
-        {
-            use std;
-            use colored::*;
-            println!(concat!("{}: ", $fmt), if cfg!(windows) { "error".normal() } else { "error".bold().red() }, $($arg)*);
-            std::process::exit(1);
-        }
-    };
-}
+use std::cmp::Ordering::Equal;
 
-macro_rules! warning {
-    ($fmt:expr) => {
-        {
-            use colored::*;
-            println!(concat!("{}: ", $fmt), if cfg!(windows) { "warning".normal() } else { "warning".bold().yellow() });
-        }
-    };
-    ($fmt:expr, $($arg:tt)*) => {
-        use colored::*;
-        println!(concat!("{}: ", $fmt), if cfg!(windows) { "warning".normal() } else { "warning".bold().yellow() }, $($arg)*);
-    };
-}
+use serde_json;
 
 pub fn split_name(name: &str) -> (String, String) {
     let mut parts: Vec<&str> = name.split('/').collect();
@@ -62,9 +34,18 @@ pub fn bind_axis(x: &[f64], y: &[f64]) -> Vec<(f64, f64)> {
     points
 }
 
+/// Drops gaps so derived data is computed between the samples that were logged
+fn present(orig: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    orig.iter()
+        .filter(|p| !p.0.is_nan() && !p.1.is_nan())
+        .cloned()
+        .collect()
+}
+
 pub fn differention(orig: &[(f64, f64)]) -> Vec<(f64, f64)> {
-    let mut out = Vec::with_capacity(orig.len() - 1);
-    for i in 0..orig.len() - 1 {
+    let orig = &present(orig)[..];
+    let mut out = Vec::with_capacity(orig.len().saturating_sub(1));
+    for i in 0..orig.len().saturating_sub(1) {
         let (x1, y1) = orig[i];
         let (x2, y2) = orig[i + 1];
         let x = (x1 + x2) / 2f64;
@@ -75,8 +56,9 @@ pub fn differention(orig: &[(f64, f64)]) -> Vec<(f64, f64)> {
 }
 
 pub fn delta(orig: &[(f64, f64)]) -> Vec<(f64, f64)> {
-    let mut out = Vec::with_capacity(orig.len() - 1);
-    for i in 0..orig.len() - 1 {
+    let orig = &present(orig)[..];
+    let mut out = Vec::with_capacity(orig.len().saturating_sub(1));
+    for i in 0..orig.len().saturating_sub(1) {
         let (x1, y1) = orig[i];
         let (x2, y2) = orig[i + 1];
         let x = (x1 + x2) / 2f64;
@@ -87,7 +69,8 @@ pub fn delta(orig: &[(f64, f64)]) -> Vec<(f64, f64)> {
 }
 
 pub fn integration(orig: &[(f64, f64)]) -> (Vec<(f64, f64)>, f64) {
-    let mut out = Vec::with_capacity(orig.len() - 1);
+    let orig = &present(orig)[..];
+    let mut out = Vec::with_capacity(orig.len().saturating_sub(1));
     let mut total_area = 0f64;
     for i in 1..orig.len() {
         // Trapazoid rule integration
@@ -103,8 +86,228 @@ pub fn integration(orig: &[(f64, f64)]) -> (Vec<(f64, f64)>, f64) {
     (out, total_area)
 }
 
+/// Splits a `<name>=<value>` command line option, `None` when there is no name
+pub fn split_option(option_text: &str) -> Option<(String, String)> {
+    match option_text.find('=') {
+        Some(split) if split > 0 => Option::Some((
+            option_text[..split].trim().to_string(),
+            option_text[split + 1..].trim().to_string(),
+        )),
+        _ => Option::None,
+    }
+}
+
+/// Value of the line through `orig` at `x`, interpolated between its neighbouring points.
+/// `orig` has to be sorted by x, outside of it and next to a gap the value is `NAN`.
+pub fn resample(orig: &[(f64, f64)], x: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    let i = match orig.binary_search_by(|p| p.0.partial_cmp(&x).unwrap_or(Equal)) {
+        Ok(i) => return orig[i].1,
+        Err(i) => i,
+    };
+    if i == 0 || i == orig.len() {
+        return f64::NAN;
+    }
+    let (x1, y1) = orig[i - 1];
+    let (x2, y2) = orig[i];
+    y1 + (y2 - y1) * (x - x1) / (x2 - x1)
+}
+
+/// Shrinks a line to about `target` points by keeping the lowest and highest point of each of
+/// `target / 2` buckets, so spikes survive. The first and last points and a gap in each bucket
+/// are kept too.
+pub fn decimate(orig: &[(f64, f64)], target: usize) -> Vec<(f64, f64)> {
+    if orig.len() <= target || orig.len() < 3 {
+        return orig.to_vec();
+    }
+
+    let buckets = (target / 2).max(1);
+    let size = orig.len() as f64 / buckets as f64;
+    let mut keep: Vec<usize> = vec![0];
+    for bucket in 0..buckets {
+        let from = (bucket as f64 * size) as usize;
+        let to = (((bucket + 1) as f64 * size) as usize).min(orig.len());
+
+        let mut low: Option<usize> = Option::None;
+        let mut high: Option<usize> = Option::None;
+        let mut gap: Option<usize> = Option::None;
+        for i in from..to {
+            let y = orig[i].1;
+            if y.is_nan() {
+                gap = gap.or(Some(i));
+                continue;
+            }
+            if low.is_none_or(|low| y < orig[low].1) {
+                low = Some(i);
+            }
+            if high.is_none_or(|high| y > orig[high].1) {
+                high = Some(i);
+            }
+        }
+
+        let mut picked: Vec<usize> = vec![low, high, gap].into_iter().flatten().collect();
+        picked.sort();
+        keep.extend(picked);
+    }
+    keep.push(orig.len() - 1);
+    keep.dedup();
+
+    keep.into_iter().map(|i| orig[i]).collect()
+}
+
+/// Drops the points of a step line that repeat the value before them, which doesn't change
+/// how it is drawn
+pub fn step_changes(orig: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut out: Vec<(f64, f64)> = Vec::with_capacity(orig.len());
+    for (i, &(x, y)) in orig.iter().enumerate() {
+        let repeat = i > 0 && i + 1 < orig.len() && {
+            let last = orig[i - 1].1;
+            last == y || (last.is_nan() && y.is_nan())
+        };
+        if !repeat {
+            out.push((x, y));
+        }
+    }
+    out
+}
+
+/// Makes text safe to put in HTML content and quoted attributes
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Makes text safe to put in Markdown, including table cells
+pub fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' | '!' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Markdown code block, the fence is longer than any run of backticks in the text
+pub fn markdown_code_block(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat((longest + 1).max(3));
+    format!("{fence}\n{text}\n{fence}\n", fence = fence, text = text)
+}
+
+/// Markdown table with a header row, every cell is escaped
+pub fn markdown_table(head: &[&str], rows: &[Vec<String>]) -> String {
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut out = line(head.iter().map(|h| escape_markdown(h)).collect());
+    out += &line(head.iter().map(|_| "---".to_string()).collect());
+    for row in rows {
+        out += &line(row.iter().map(|cell| escape_markdown(cell)).collect());
+    }
+    out
+}
+
+/// JSON for a `<script>` element, `<` is escaped so no string can end the element
+pub fn script_json(value: &serde_json::Value) -> String {
+    value.to_string().replace('<', "\\u003c")
+}
+
 pub fn hash_string(string: &str) -> String {
     let mut m = sha1::Sha1::new();
     m.update(string.as_bytes());
     m.digest().to_string()
 }
+
+/// Matches `name` against a pattern where `*` stands for any text, including `/`, and `?` for one character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is retried from
+    let mut retry: Option<(usize, usize)> = Option::None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            retry = Some((p, n));
+        } else if let Some((retry_p, retry_n)) = retry {
+            p = retry_p;
+            n = retry_n + 1;
+            retry = Some((retry_p, retry_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Points to plot with gaps as `NAN`, which are written as `null` to break the line
+pub fn points(data: &[(f64, f64)], offset: f64) -> Vec<(f64, f64)> {
+    data.iter()
+        .filter(|p| !p.0.is_nan())
+        .map(|&(x, y)| (x, y + offset))
+        .collect()
+}
+
+/// Counts `(rising, falling)` edges, gaps are skipped over
+pub fn digital_edges(data: &[(f64, f64)]) -> (usize, usize) {
+    let mut rising = 0;
+    let mut falling = 0;
+    let mut last: Option<bool> = Option::None;
+    for &(_, y) in data.iter().filter(|p| !p.1.is_nan()) {
+        let high = y > 0.5;
+        match last {
+            Some(false) if high => rising += 1,
+            Some(true) if !high => falling += 1,
+            _ => (),
+        }
+        last = Some(high);
+    }
+    (rising, falling)
+}
+
+/// Runs of the same state as `(state, from, to)`, each ending where the next one starts
+pub fn state_segments(data: &[(f64, f64)]) -> Vec<(usize, f64, f64)> {
+    let mut segments: Vec<(usize, f64, f64)> = Vec::new();
+    // A gap ends the current segment
+    let mut open = false;
+    for &(x, y) in data.iter().filter(|p| !p.0.is_nan()) {
+        if open {
+            segments.last_mut().unwrap().2 = x;
+        }
+        if y.is_nan() {
+            open = false;
+            continue;
+        }
+        let state = y as usize;
+        if !open || segments.last().is_none_or(|last| last.0 != state) {
+            segments.push((state, x, x));
+        }
+        open = true;
+    }
+    segments
+}
@@ -0,0 +1,178 @@
+use std::convert::TryFrom;
+
+use csv;
+
+use error::{Error, Result};
+use input::{Input, Topic};
+use xaxis::XAxis;
+
+/// One end of a window
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Bound {
+    /// Position on the x-axis
+    X(f64),
+    Row(usize),
+}
+
+impl Bound {
+    pub fn from(bound_text: &str) -> Result<Bound> {
+        if bound_text.starts_with("row:") {
+            let (_, right) = bound_text.split_at(4);
+            if let Ok(row) = right.trim().parse::<usize>() {
+                return Result::Ok(Bound::Row(row));
+            }
+        } else if let Ok(x) = bound_text.trim().parse::<f64>() {
+            if !x.is_nan() {
+                return Result::Ok(Bound::X(x));
+            }
+        }
+
+        Result::Err(Error::UnknownBound(bound_text.to_string()))
+    }
+}
+
+/// Part of a log to keep, both ends are included
+#[derive(Debug, PartialEq, Clone)]
+pub enum Window {
+    Range {
+        from: Option<Bound>,
+        to: Option<Bound>,
+    },
+    /// From the first to the last row a boolean topic is true in
+    While(String),
+}
+
+/// Rows of the input, a sparse topic only has values in some of them
+fn row_count(input: &Input) -> Result<usize> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(input.csv_text.as_bytes());
+    let mut rows = 0;
+    for record in reader.records() {
+        record?;
+        rows += 1;
+    }
+    Result::Ok(rows)
+}
+
+/// Whether a topic has a value in every row, instead of only where its own time column does
+fn is_dense(topic: &Topic, topics: &[Topic]) -> bool {
+    topic.xaxis_topic().is_none()
+        && !topics
+            .iter()
+            .any(|t| t.xaxis_topic().is_some_and(|name| name.eq(&topic.name)))
+}
+
+impl Window {
+    /// First and last row to keep
+    fn rows(&self, input: &Input, xaxis: &XAxis, rows: usize) -> Result<(usize, usize)> {
+        let (from, to) = match *self {
+            Window::Range { from, to } => (from, to),
+            Window::While(ref name) => {
+                let topic = match input.topics.iter().find(|t| t.name.eq(name)) {
+                    Some(topic) if is_dense(topic, &input.topics) => topic,
+                    _ => return Result::Err(Error::WindowTopic(name.clone())),
+                };
+                let mut high = topic.data.iter().enumerate().filter(|p| *p.1 > 0.5);
+                return match (high.next(), high.next_back()) {
+                    (Some(first), Some(last)) => Result::Ok((first.0, last.0)),
+                    (Some(only), None) => Result::Ok((only.0, only.0)),
+                    _ => Result::Err(Error::EmptyWindow),
+                };
+            }
+        };
+
+        // Rows without a position on the axis are kept when they are inside the window
+        let first = match from {
+            None => Option::Some(0),
+            Some(Bound::Row(row)) => Option::Some(row),
+            Some(Bound::X(x)) => (0..rows).find(|row| xaxis.at(*row) >= x),
+        };
+        let last = match to {
+            None => rows.checked_sub(1),
+            Some(Bound::Row(row)) => Option::Some(row.min(rows.saturating_sub(1))),
+            Some(Bound::X(x)) => (0..rows).rev().find(|row| xaxis.at(*row) <= x),
+        };
+        match (first, last) {
+            (Some(first), Some(last)) if first <= last && first < rows => Result::Ok((first, last)),
+            _ => Result::Err(Error::EmptyWindow),
+        }
+    }
+
+    /// Crops every topic, log and the CSV of `input` to the window, returning a description of
+    /// where it is on the x-axis. Topics with their own time column keep the values logged in
+    /// the same rows.
+    pub fn crop(&self, input: &mut Input) -> Result<String> {
+        let xaxis = XAxis::try_from(&input.topics[..]).unwrap_or_else(|_| XAxis::index());
+        let rows = row_count(input)?;
+        let (first, last) = self.rows(input, &xaxis, rows)?;
+        let (x_from, x_to) = (xaxis.at(first), xaxis.at(last));
+
+        let dense: Vec<bool> = input
+            .topics
+            .iter()
+            .map(|t| is_dense(t, &input.topics))
+            .collect();
+
+        for (i, topic) in input.topics.iter_mut().enumerate() {
+            if dense[i] {
+                if topic.data.len() > first {
+                    let end = (last + 1).min(topic.data.len());
+                    topic.data = topic.data[first..end].to_vec();
+                } else {
+                    topic.data.clear();
+                }
+            } else {
+                // Topics that weren't read from a CSV have a sample in every row
+                if topic.rows.len() != topic.data.len() {
+                    topic.rows = (0..topic.data.len()).collect();
+                }
+                // Sparse topics keep the samples logged in the window's rows
+                let samples: Vec<(usize, f64)> = topic
+                    .rows
+                    .iter()
+                    .zip(&topic.data)
+                    .filter(|p| *p.0 >= first && *p.0 <= last)
+                    .map(|p| (*p.0 - first, *p.1))
+                    .collect();
+                topic.rows = samples.iter().map(|p| p.0).collect();
+                topic.data = samples.iter().map(|p| p.1).collect();
+            }
+            topic.missing = topic.data.iter().filter(|v| v.is_nan()).count();
+        }
+
+        for log in &mut input.logs {
+            log.data = log
+                .data
+                .iter()
+                .filter(|line| line.0 as usize >= first && line.0 as usize <= last)
+                .map(|line| (line.0 - first as u64, line.1.clone()))
+                .collect();
+        }
+
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(input.csv_text.as_bytes());
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(Vec::new());
+        writer.write_record(reader.headers()?)?;
+        for record in reader.records().skip(first).take(last + 1 - first) {
+            writer.write_record(&record?)?;
+        }
+        input.csv_text = match writer.into_inner() {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => return Result::Err(Error::Csv(e.into_error().into())),
+        };
+
+        let span = if xaxis.data.is_some() {
+            format!("{} to {} {}", x_from, x_to, xaxis.unit)
+        } else {
+            format!("rows {} to {}", first, last)
+        };
+        Result::Ok(match *self {
+            Window::While(ref name) => format!("{} while {} is true", span, name),
+            Window::Range { .. } => span,
+        })
+    }
+}
@@ -0,0 +1,627 @@
+use std::collections::HashMap;
+use std::io::prelude::*;
+
+use csv;
+
+use attribute::Attribute;
+use error::{Diagnostics, Error, Result};
+use input::{Input, Log, Topic, Value};
+
+pub const WPILOG_MAGIC: &[u8] = b"WPILOG";
+
+const CONTROL_ENTRY: u32 = 0;
+const CONTROL_START: u8 = 0;
+const CONTROL_FINISH: u8 = 1;
+const CONTROL_SET_METADATA: u8 = 2;
+
+const TIME_TOPIC: &str = "Time";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Kind {
+    Boolean,
+    Int64,
+    Float,
+    Double,
+    String,
+    BooleanArray,
+    Int64Array,
+    FloatArray,
+    DoubleArray,
+    StringArray,
+}
+
+impl Kind {
+    fn from(type_text: &str) -> Option<Kind> {
+        match type_text {
+            "boolean" => Some(Kind::Boolean),
+            "int64" => Some(Kind::Int64),
+            "float" => Some(Kind::Float),
+            "double" => Some(Kind::Double),
+            "string" => Some(Kind::String),
+            "boolean[]" => Some(Kind::BooleanArray),
+            "int64[]" => Some(Kind::Int64Array),
+            "float[]" => Some(Kind::FloatArray),
+            "double[]" => Some(Kind::DoubleArray),
+            "string[]" => Some(Kind::StringArray),
+            _ => None,
+        }
+    }
+
+    fn is_text(self) -> bool {
+        self == Kind::String || self == Kind::StringArray
+    }
+
+    fn is_bool(self) -> bool {
+        self == Kind::Boolean || self == Kind::BooleanArray
+    }
+}
+
+#[derive(Debug)]
+enum Sample {
+    Numbers(Vec<f64>),
+    Text(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    kind: Kind,
+    samples: Vec<(u64, Sample)>,
+}
+
+/// Cursor over the raw bytes of a wpilog file
+struct Records<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Records<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() - self.pos < len {
+            return Result::Err(Error::Wpilog(format!(
+                "unexpected end of file at byte {}",
+                self.pos
+            )));
+        }
+        let out = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Result::Ok(out)
+    }
+
+    fn uint(&mut self, len: usize) -> Result<u64> {
+        Result::Ok(le_uint(self.take(len)?))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+}
+
+fn le_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte))
+}
+
+/// Reads a `u32` length prefixed string out of a control record or string array
+fn read_string(payload: &mut Records) -> Result<String> {
+    let len = payload.uint(4)? as usize;
+    Result::Ok(String::from_utf8_lossy(payload.take(len)?).into_owned())
+}
+
+fn decode(kind: Kind, payload: &[u8]) -> Result<Sample> {
+    let numbers = |size: usize, convert: &dyn Fn(&[u8]) -> f64| -> Sample {
+        Sample::Numbers(
+            payload
+                .chunks(size)
+                .filter(|c| c.len() == size)
+                .map(convert)
+                .collect(),
+        )
+    };
+    let boolean = |bytes: &[u8]| if bytes[0] == 0 { 0f64 } else { 1f64 };
+    let int64 = |bytes: &[u8]| le_uint(bytes) as i64 as f64;
+    let float = |bytes: &[u8]| f64::from(f32::from_bits(le_uint(bytes) as u32));
+    let double = |bytes: &[u8]| f64::from_bits(le_uint(bytes));
+
+    Result::Ok(match kind {
+        Kind::Boolean | Kind::BooleanArray => numbers(1, &boolean),
+        Kind::Int64 | Kind::Int64Array => numbers(8, &int64),
+        Kind::Float | Kind::FloatArray => numbers(4, &float),
+        Kind::Double | Kind::DoubleArray => numbers(8, &double),
+        Kind::String => Sample::Text(String::from_utf8_lossy(payload).into_owned()),
+        Kind::StringArray => {
+            let mut records = Records {
+                bytes: payload,
+                pos: 0,
+            };
+            let count = records.uint(4)?;
+            let mut parts = Vec::new();
+            for _ in 0..count {
+                parts.push(read_string(&mut records)?);
+            }
+            Sample::Text(parts.join(", "))
+        }
+    })
+}
+
+/// Log names usually start with a slash, which would otherwise become an empty folder
+fn entry_name(name: &str) -> String {
+    name.trim_start_matches('/').to_string()
+}
+
+fn read_entries(bytes: &[u8], diag: &mut Diagnostics) -> Result<(Vec<Entry>, String)> {
+    let mut records = Records { bytes, pos: 0 };
+
+    if records.take(WPILOG_MAGIC.len())? != WPILOG_MAGIC {
+        return Result::Err(Error::Wpilog("missing WPILOG header".to_string()));
+    }
+    let version = records.uint(2)?;
+    if version >> 8 != 1 {
+        return Result::Err(Error::Wpilog(format!(
+            "unsupported version {}.{}",
+            version >> 8,
+            version & 0xff
+        )));
+    }
+    let extra_header_len = records.uint(4)? as usize;
+    let extra_header = String::from_utf8_lossy(records.take(extra_header_len)?).into_owned();
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut active: HashMap<u32, usize> = HashMap::new();
+
+    while !records.is_empty() {
+        let lengths = records.take(1)?[0];
+        let entry_id = records.uint(usize::from(lengths & 0x3) + 1)? as u32;
+        let payload_len = records.uint(usize::from((lengths >> 2) & 0x3) + 1)? as usize;
+        let timestamp = records.uint(usize::from((lengths >> 4) & 0x7) + 1)?;
+        let payload = records.take(payload_len)?;
+
+        if entry_id == CONTROL_ENTRY {
+            let mut control = Records {
+                bytes: payload,
+                pos: 0,
+            };
+            match control.take(1)?[0] {
+                CONTROL_START => {
+                    let id = control.uint(4)? as u32;
+                    let name = entry_name(&read_string(&mut control)?);
+                    let type_text = read_string(&mut control)?;
+                    match Kind::from(&type_text) {
+                        Some(kind) => {
+                            let index = match entries.iter().position(|e| e.name.eq(&name)) {
+                                Some(index) => {
+                                    if entries[index].kind != kind {
+                                        diag.warn(format!(
+                                            "Entry {} restarted with type {}, skipping it",
+                                            name, type_text
+                                        ));
+                                        active.remove(&id);
+                                        continue;
+                                    }
+                                    index
+                                }
+                                None => {
+                                    entries.push(Entry {
+                                        name,
+                                        kind,
+                                        samples: Vec::new(),
+                                    });
+                                    entries.len() - 1
+                                }
+                            };
+                            active.insert(id, index);
+                        }
+                        None => {
+                            diag.warn(format!(
+                                "Skipping entry {} with unsupported type {}",
+                                name, type_text
+                            ));
+                            active.remove(&id);
+                        }
+                    }
+                }
+                CONTROL_FINISH => {
+                    active.remove(&(control.uint(4)? as u32));
+                }
+                CONTROL_SET_METADATA => (),
+                other => {
+                    return Result::Err(Error::Wpilog(format!(
+                        "unknown control record type {}",
+                        other
+                    )))
+                }
+            }
+            continue;
+        }
+
+        if let Some(&index) = active.get(&entry_id) {
+            let sample = decode(entries[index].kind, payload)?;
+            entries[index].samples.push((timestamp, sample));
+        }
+    }
+
+    Result::Ok((entries, extra_header))
+}
+
+/// Puts every entry on one shared x-axis made of all record timestamps.
+///
+/// Numeric values are held from one record to the next, rows before an entry's
+/// first record are gaps.
+pub fn parse_wpilog<R: Read>(
+    mut reader: R,
+    input_path: &str,
+    diag: &mut Diagnostics,
+) -> Result<Input> {
+    let mut bytes = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut bytes) {
+        return Result::Err(Error::Io(input_path.to_string(), e));
+    }
+
+    let (entries, extra_header) = read_entries(&bytes, diag)?;
+
+    let mut timestamps: Vec<u64> = entries
+        .iter()
+        .flat_map(|e| e.samples.iter().map(|s| s.0))
+        .collect();
+    timestamps.sort();
+    timestamps.dedup();
+    let row_of = |timestamp: u64| timestamps.binary_search(&timestamp).unwrap();
+
+    let mut time = Topic::from(TIME_TOPIC);
+    time.unit = "s".to_string();
+    time.attrs = vec![Attribute::Xaxis, Attribute::Hide];
+    time.data = timestamps.iter().map(|t| *t as f64 / 1e6).collect();
+
+    let mut topics = vec![time];
+    let mut logs: Vec<Log> = Vec::new();
+
+    for entry in &entries {
+        if entry.name.eq(TIME_TOPIC) {
+            diag.warn(format!(
+                "Entry {} clashes with the timestamp topic, skipping it",
+                entry.name
+            ));
+            continue;
+        }
+
+        if entry.kind.is_text() {
+            let mut log = Log::from(Topic::from(entry.name.as_str()));
+            log.attrs = vec![Attribute::Log];
+            for &(timestamp, ref sample) in &entry.samples {
+                if let Sample::Text(ref text) = *sample {
+                    if !text.is_empty() {
+                        log.data.push((row_of(timestamp) as u64, text.clone()));
+                    }
+                }
+            }
+            log.data.sort_by_key(|line| line.0);
+            logs.push(log);
+            continue;
+        }
+
+        let width = entry
+            .samples
+            .iter()
+            .map(|s| match s.1 {
+                Sample::Numbers(ref numbers) => numbers.len(),
+                Sample::Text(_) => 0,
+            })
+            .max()
+            .unwrap_or(0);
+        let is_array = !matches!(
+            entry.kind,
+            Kind::Boolean | Kind::Int64 | Kind::Float | Kind::Double
+        );
+
+        for element in 0..width {
+            let mut column: Vec<Option<f64>> = vec![None; timestamps.len()];
+            for &(timestamp, ref sample) in &entry.samples {
+                if let Sample::Numbers(ref numbers) = *sample {
+                    if let Some(value) = numbers.get(element) {
+                        column[row_of(timestamp)] = Some(*value);
+                    }
+                }
+            }
+
+            let mut held = f64::NAN;
+            let data: Vec<f64> = column
+                .into_iter()
+                .map(|value| {
+                    if let Some(value) = value {
+                        held = value;
+                    }
+                    held
+                })
+                .collect();
+
+            let name = if is_array {
+                format!("{}/{}", entry.name, element)
+            } else {
+                entry.name.clone()
+            };
+            let mut topic = Topic::from(name.as_str());
+            if entry.kind.is_bool() {
+                topic.attrs.push(Attribute::Bool);
+            }
+            topic.missing = data.iter().filter(|v| v.is_nan()).count();
+            topic.data = data;
+            topics.push(topic);
+        }
+    }
+
+    let values = if extra_header.is_empty() {
+        Vec::new()
+    } else {
+        vec![Value {
+            name: "Header".to_string(),
+            name_base: "Header".to_string(),
+            name_folder: "".to_string(),
+            value: extra_header,
+            run: Option::None,
+        }]
+    };
+
+    let csv_text = gen_csv(&topics, &logs, timestamps.len())?;
+
+    Result::Ok(Input {
+        topics,
+        logs,
+        values,
+        json_header_text: Option::None,
+        csv_text,
+    })
+}
+
+/// Flattened table of the decoded log for the report's CSV download
+fn gen_csv(topics: &[Topic], logs: &[Log], rows: usize) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let header: Vec<&str> = topics
+        .iter()
+        .map(|t| t.name.as_str())
+        .chain(logs.iter().map(|l| l.name.as_str()))
+        .collect();
+    writer.write_record(&header)?;
+
+    let mut log_positions = vec![0usize; logs.len()];
+    for row in 0..rows {
+        let mut record: Vec<String> = topics
+            .iter()
+            .map(|t| {
+                let value = t.data[row];
+                if value.is_nan() {
+                    String::new()
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect();
+        for (log, position) in logs.iter().zip(log_positions.iter_mut()) {
+            let mut cell = String::new();
+            while *position < log.data.len() && log.data[*position].0 as usize == row {
+                if !cell.is_empty() {
+                    cell.push_str("; ");
+                }
+                cell.push_str(&log.data[*position].1);
+                *position += 1;
+            }
+            record.push(cell);
+        }
+        writer.write_record(&record)?;
+    }
+
+    let bytes = match writer.into_inner() {
+        Ok(bytes) => bytes,
+        Err(e) => return Result::Err(Error::Wpilog(e.to_string())),
+    };
+    Result::Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes needed to store `value`, records use the smallest width that fits
+    fn width(value: u64) -> usize {
+        (1..8).find(|n| value >> (8 * n) == 0).unwrap_or(8)
+    }
+
+    fn le_bytes(value: u64, len: usize) -> Vec<u8> {
+        (0..len).map(|i| (value >> (8 * i)) as u8).collect()
+    }
+
+    fn log_bytes(extra_header: &str) -> Vec<u8> {
+        let mut out = WPILOG_MAGIC.to_vec();
+        out.extend(le_bytes(0x0100, 2));
+        out.extend(le_bytes(extra_header.len() as u64, 4));
+        out.extend(extra_header.as_bytes());
+        out
+    }
+
+    fn record(out: &mut Vec<u8>, id: u32, timestamp: u64, payload: &[u8]) {
+        let (id_len, len_len) = (
+            width(u64::from(id)).min(4),
+            width(payload.len() as u64).min(4),
+        );
+        let time_len = width(timestamp);
+        out.push(((id_len - 1) | (len_len - 1) << 2 | (time_len - 1) << 4) as u8);
+        out.extend(le_bytes(u64::from(id), id_len));
+        out.extend(le_bytes(payload.len() as u64, len_len));
+        out.extend(le_bytes(timestamp, time_len));
+        out.extend(payload);
+    }
+
+    fn string(text: &str) -> Vec<u8> {
+        let mut out = le_bytes(text.len() as u64, 4);
+        out.extend(text.as_bytes());
+        out
+    }
+
+    fn start(out: &mut Vec<u8>, id: u32, name: &str, type_text: &str) {
+        let mut payload = vec![CONTROL_START];
+        payload.extend(le_bytes(u64::from(id), 4));
+        payload.extend(string(name));
+        payload.extend(string(type_text));
+        payload.extend(string(""));
+        record(out, CONTROL_ENTRY, 0, &payload);
+    }
+
+    fn finish(out: &mut Vec<u8>, id: u32) {
+        let mut payload = vec![CONTROL_FINISH];
+        payload.extend(le_bytes(u64::from(id), 4));
+        record(out, CONTROL_ENTRY, 0, &payload);
+    }
+
+    fn double(value: f64) -> Vec<u8> {
+        le_bytes(value.to_bits(), 8)
+    }
+
+    fn parse(bytes: &[u8]) -> (Result<Input>, Diagnostics) {
+        let mut diag = Diagnostics::new();
+        let input = parse_wpilog(bytes, "test.wpilog", &mut diag);
+        (input, diag)
+    }
+
+    fn topic<'a>(input: &'a Input, name: &str) -> &'a Topic {
+        input.topics.iter().find(|t| t.name.eq(name)).unwrap()
+    }
+
+    /// Compares samples with gaps, which `NAN` doesn't equal
+    fn assert_samples(data: &[f64], expected: &[Option<f64>]) {
+        let data: Vec<Option<f64>> = data
+            .iter()
+            .map(|v| if v.is_nan() { None } else { Some(*v) })
+            .collect();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn holds_values_and_leaves_gaps_before_the_first_record() {
+        let mut bytes = log_bytes("");
+        start(&mut bytes, 1, "/Arm/Position", "double");
+        start(&mut bytes, 2, "/Count", "int64");
+        record(&mut bytes, 1, 1000, &double(1.5));
+        record(&mut bytes, 2, 2000, &le_bytes(7, 8));
+        record(&mut bytes, 1, 3000, &double(-2.5));
+
+        let (input, diag) = parse(&bytes);
+        let input = input.unwrap();
+        assert!(diag.warnings.is_empty());
+
+        let time = topic(&input, TIME_TOPIC);
+        assert_eq!(time.data, vec![0.001, 0.002, 0.003]);
+        assert!(time.attrs.contains(&Attribute::Xaxis));
+
+        let position = topic(&input, "Arm/Position");
+        assert_samples(&position.data, &[Some(1.5), Some(1.5), Some(-2.5)]);
+        assert_eq!(position.missing, 0);
+
+        let count = topic(&input, "Count");
+        assert_samples(&count.data, &[None, Some(7.0), Some(7.0)]);
+        assert_eq!(count.missing, 1);
+    }
+
+    #[test]
+    fn follows_start_finish_and_restart() {
+        let mut bytes = log_bytes("");
+        start(&mut bytes, 1, "/Speed", "double");
+        record(&mut bytes, 1, 100, &double(1.0));
+        finish(&mut bytes, 1);
+        // Records of a finished entry are ignored
+        record(&mut bytes, 1, 200, &double(9.0));
+        // Restarting under a new id continues the same topic
+        start(&mut bytes, 2, "/Speed", "double");
+        record(&mut bytes, 2, 300, &double(3.0));
+        // A restart with another type is skipped
+        start(&mut bytes, 3, "/Speed", "int64");
+        record(&mut bytes, 3, 400, &le_bytes(5, 8));
+
+        let (input, diag) = parse(&bytes);
+        let input = input.unwrap();
+        assert_eq!(diag.warnings.len(), 1);
+        assert!(diag.warnings[0].contains("restarted with type int64"));
+
+        assert_eq!(topic(&input, TIME_TOPIC).data, vec![0.0001, 0.0003]);
+        assert_samples(&topic(&input, "Speed").data, &[Some(1.0), Some(3.0)]);
+        assert_eq!(input.topics.len(), 2);
+    }
+
+    #[test]
+    fn splits_arrays_and_reads_text() {
+        let mut bytes = log_bytes("team 1234");
+        start(&mut bytes, 1, "/Swerve/Angles", "double[]");
+        start(&mut bytes, 2, "/Flags", "boolean[]");
+        start(&mut bytes, 3, "/Messages", "string[]");
+        start(&mut bytes, 4, "/Enabled", "boolean");
+        start(&mut bytes, 5, "/Struct", "struct:Pose2d");
+
+        let mut angles = double(0.5);
+        angles.extend(double(1.5));
+        record(&mut bytes, 1, 10, &angles);
+        record(&mut bytes, 2, 10, &[1, 0]);
+        let mut messages = le_bytes(2, 4);
+        messages.extend(string("a"));
+        messages.extend(string("b"));
+        record(&mut bytes, 3, 20, &messages);
+        record(&mut bytes, 4, 20, &[1]);
+        record(&mut bytes, 5, 20, &[0; 24]);
+
+        let (input, diag) = parse(&bytes);
+        let input = input.unwrap();
+        assert_eq!(diag.warnings.len(), 1);
+        assert!(diag.warnings[0].contains("unsupported type struct:Pose2d"));
+
+        assert_samples(
+            &topic(&input, "Swerve/Angles/0").data,
+            &[Some(0.5), Some(0.5)],
+        );
+        assert_samples(
+            &topic(&input, "Swerve/Angles/1").data,
+            &[Some(1.5), Some(1.5)],
+        );
+        assert!(!topic(&input, "Swerve/Angles/0")
+            .attrs
+            .contains(&Attribute::Bool));
+
+        assert_samples(&topic(&input, "Flags/0").data, &[Some(1.0), Some(1.0)]);
+        assert_samples(&topic(&input, "Flags/1").data, &[Some(0.0), Some(0.0)]);
+        assert!(topic(&input, "Flags/1").attrs.contains(&Attribute::Bool));
+        assert_samples(&topic(&input, "Enabled").data, &[None, Some(1.0)]);
+        assert!(topic(&input, "Enabled").attrs.contains(&Attribute::Bool));
+
+        assert_eq!(input.logs.len(), 1);
+        assert_eq!(input.logs[0].name, "Messages");
+        assert_eq!(input.logs[0].data, vec![(1, "a, b".to_string())]);
+
+        assert_eq!(input.values.len(), 1);
+        assert_eq!(input.values[0].value, "team 1234");
+    }
+
+    #[test]
+    fn reports_a_truncated_tail() {
+        let mut bytes = log_bytes("");
+        start(&mut bytes, 1, "/Speed", "double");
+        record(&mut bytes, 1, 100, &double(1.0));
+        bytes.truncate(bytes.len() - 3);
+
+        match parse(&bytes).0 {
+            Err(Error::Wpilog(ref message)) => {
+                assert!(message.contains("unexpected end of file"), "{}", message)
+            }
+            other => panic!("expected a wpilog error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = log_bytes("");
+        bytes[WPILOG_MAGIC.len() + 1] = 2;
+
+        match parse(&bytes).0 {
+            Err(Error::Wpilog(ref message)) => assert!(message.contains("unsupported version")),
+            other => panic!("expected a wpilog error, got {:?}", other),
+        }
+    }
+}
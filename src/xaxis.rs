@@ -1,4 +1,7 @@
+use std::convert::TryFrom;
+
 use attribute::Attribute;
+use error::Error;
 use input::Topic;
 
 #[derive(Debug)]
@@ -8,14 +11,61 @@ pub struct XAxis {
     pub data: Option<Vec<f64>>,
 }
 
-impl From<&[Topic]> for XAxis {
-    fn from(topics: &[Topic]) -> Self {
+impl XAxis {
+    pub fn index() -> Self {
+        XAxis {
+            name: "Index".to_string(),
+            unit: ::UNITLESS.to_string(),
+            data: Option::None,
+        }
+    }
+
+    pub fn from_topic(topic: &Topic) -> Self {
+        XAxis {
+            name: format!("{} ({})", topic.name_base, topic.unit),
+            unit: topic.unit.clone(),
+            data: Option::Some(topic.data.clone()),
+        }
+    }
+
+    /// Position of the given row on this axis
+    pub fn at(&self, row: usize) -> f64 {
+        match self.data {
+            Some(ref data) => data[row],
+            None => f64::from(row as u32),
+        }
+    }
+
+    /// Copy of this axis with only the positions of `rows`
+    pub fn at_rows(&self, rows: &[usize]) -> Self {
+        XAxis {
+            name: self.name.clone(),
+            unit: self.unit.clone(),
+            data: Option::Some(rows.iter().map(|row| self.at(*row)).collect()),
+        }
+    }
+
+    /// Copy of this axis with `offset` subtracted, an index axis gets explicit data for `len` rows
+    pub fn shifted(&self, offset: f64, len: usize) -> Self {
+        let data = (0..len).map(|row| self.at(row) - offset).collect();
+        XAxis {
+            name: self.name.clone(),
+            unit: self.unit.clone(),
+            data: Option::Some(data),
+        }
+    }
+}
+
+impl TryFrom<&[Topic]> for XAxis {
+    type Error = Error;
+
+    fn try_from(topics: &[Topic]) -> Result<Self, Error> {
         let xaxis_index: Option<usize> = {
             let mut out = Option::None;
             for (i, topic) in topics.iter().enumerate() {
                 if topic.attrs.contains(&Attribute::Xaxis) {
                     if out.is_some() {
-                        error!("Multiple topics with xaxis attribute");
+                        return Result::Err(Error::MultipleXaxis);
                     } else {
                         out = Some(i);
                     }
@@ -25,21 +75,9 @@ impl From<&[Topic]> for XAxis {
         };
 
         if let Some(xaxis_index) = xaxis_index {
-            let unit_text = format!(
-                "{} ({})",
-                topics[xaxis_index].name_base, topics[xaxis_index].unit
-            );
-            XAxis {
-                name: unit_text,
-                unit: topics[xaxis_index].unit.clone(),
-                data: Option::Some(topics[xaxis_index].data.clone()),
-            }
+            Result::Ok(XAxis::from_topic(&topics[xaxis_index]))
         } else {
-            XAxis {
-                name: "Index".to_string(),
-                unit: ::UNITLESS.to_string(),
-                data: Option::None,
-            }
+            Result::Ok(XAxis::index())
         }
     }
 }